@@ -26,7 +26,6 @@ ibus_disconnected_cb (IBusBus  *bus,
     ibus_quit ();
 }
 
-
 static void
 init (void)
 {
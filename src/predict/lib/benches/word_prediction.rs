@@ -0,0 +1,37 @@
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+use predict::config::Config;
+use predict::predict::Predictor;
+use predict::user_dictionary::UserDictionary;
+
+const PREFIXES: [&str; 5] = ["t", "th", "the", "thei", "their"];
+
+//Prefix completion time should stay about the same however short the prefix, even though the
+//shortest ones match most of the dictionary
+fn prefix_by_length(c: &mut Criterion) {
+    //the data compiled into the engine, so results are comparable between machines
+    let predictor = Predictor::embedded(&Config::default()).unwrap();
+    let user_dictionary = UserDictionary::in_memory();
+    let mut group = c.benchmark_group("prefix");
+    for prefix in PREFIXES.iter() {
+        group.bench_with_input(BenchmarkId::from_parameter(prefix), prefix, |b, prefix| {
+            b.iter(|| predictor.word_with_distance(black_box(prefix), &user_dictionary, 0).unwrap())
+        });
+    }
+    group.finish();
+}
+
+//the same with typo matching, which only kicks in for longer prefixes
+fn word_by_length(c: &mut Criterion) {
+    let predictor = Predictor::embedded(&Config::default()).unwrap();
+    let user_dictionary = UserDictionary::in_memory();
+    let mut group = c.benchmark_group("word");
+    for prefix in PREFIXES.iter() {
+        group.bench_with_input(BenchmarkId::from_parameter(prefix), prefix, |b, prefix| {
+            b.iter(|| predictor.word(black_box(prefix), &user_dictionary).unwrap())
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, prefix_by_length, word_by_length);
+criterion_main!(benches);
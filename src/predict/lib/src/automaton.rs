@@ -0,0 +1,90 @@
+use fst::Automaton;
+
+const SEGMENT_SEPARATOR: u8 = b'_';
+
+//Matches keys where any `_` separated segment (including the first) starts with the query,
+//e.g. "arrow" matches "left_right_arrow" and "arrow_up"
+pub struct SegmentPrefix<'a> {
+    query: &'a [u8]
+}
+
+#[derive(Clone)]
+pub struct SegmentPrefixState {
+    matched: bool,
+    at_segment_start: bool,
+    //bit i is set when the key so far ends with the first i bytes of the query, starting at a segment
+    progress: u64
+}
+
+impl<'a> SegmentPrefix<'a> {
+    //progress is tracked in a u64, so longer queries match nothing; no shortcode is anywhere near that long
+    const MAX_QUERY_LEN: usize = 63;
+
+    pub fn new(query: &'a str) -> SegmentPrefix<'a> {
+        SegmentPrefix {
+            query: query.as_bytes()
+        }
+    }
+}
+
+impl<'a> Automaton for SegmentPrefix<'a> {
+    type State = SegmentPrefixState;
+
+    fn start(&self) -> SegmentPrefixState {
+        SegmentPrefixState {
+            matched: self.query.is_empty(),
+            at_segment_start: true,
+            progress: 0
+        }
+    }
+
+    fn is_match(&self, state: &SegmentPrefixState) -> bool {
+        state.matched
+    }
+
+    fn can_match(&self, _state: &SegmentPrefixState) -> bool {
+        self.query.len() <= SegmentPrefix::MAX_QUERY_LEN
+    }
+
+    fn will_always_match(&self, state: &SegmentPrefixState) -> bool {
+        state.matched
+    }
+
+    fn accept(&self, state: &SegmentPrefixState, byte: u8) -> SegmentPrefixState {
+        if state.matched || !self.can_match(state) {
+            return state.clone();
+        }
+
+        let progress = if state.at_segment_start { state.progress | 1 } else { state.progress };
+        let next_progress = self.query.iter().enumerate()
+            .filter(|(idx, query_byte)| progress & (1 << idx) != 0 && **query_byte == byte)
+            .fold(0, |next, (idx, _query_byte)| next | (1 << (idx + 1)));
+
+        SegmentPrefixState {
+            matched: next_progress & (1 << self.query.len()) != 0,
+            at_segment_start: byte == SEGMENT_SEPARATOR,
+            progress: next_progress
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::automaton::SegmentPrefix;
+    use fst::{Automaton, IntoStreamer, Set};
+
+    fn matches(query: &str, keys: Vec<&str>) -> Vec<String> {
+        let set = Set::from_iter(keys).unwrap();
+        set.search(SegmentPrefix::new(query)).into_stream().into_strs().unwrap()
+    }
+
+    #[test]
+    fn segment_prefixes() {
+        let keys = vec!["arrow_up", "broken_heart", "heart", "left_right_arrow", "sparrow", "two_hearts"];
+        assert_eq!(matches("arrow", keys.clone()), vec!["arrow_up", "left_right_arrow"]);
+        assert_eq!(matches("heart", keys.clone()), vec!["broken_heart", "heart", "two_hearts"]);
+        assert_eq!(matches("right_ar", keys.clone()), vec!["left_right_arrow"]);
+        assert_eq!(matches("eart", keys.clone()), Vec::<String>::new());
+        assert!(SegmentPrefix::new("").is_match(&SegmentPrefix::new("").start()));
+    }
+}
@@ -0,0 +1,146 @@
+use std::collections::HashSet;
+use crate::candidate::Candidate;
+use crate::predict::PredictionError;
+
+//A source of word completions, e.g. the dictionaries or the bigram model
+pub trait WordPredictor {
+    //completions of prefix, best first; previous is the word typed before it, if known
+    fn words(&self, previous: Option<&str>, prefix: &str) -> Result<Vec<Candidate>, PredictionError>;
+}
+
+//A source of symbol table entries, best first
+pub trait SymbolPredictor {
+    fn symbols(&self, context: &str) -> Result<Vec<Candidate>, PredictionError>;
+}
+
+impl<P: WordPredictor + ?Sized> WordPredictor for Box<P> {
+    fn words(&self, previous: Option<&str>, prefix: &str) -> Result<Vec<Candidate>, PredictionError> {
+        (**self).words(previous, prefix)
+    }
+}
+
+impl<P: SymbolPredictor + ?Sized> SymbolPredictor for Box<P> {
+    fn symbols(&self, context: &str) -> Result<Vec<Candidate>, PredictionError> {
+        (**self).symbols(context)
+    }
+}
+
+//Combines several backends by taking their candidates in turn, best first, so each backend's top
+//results make it near the top. A candidate already given by an earlier backend is skipped, and a
+//backend that fails is left out rather than hiding the results of the others.
+pub struct Merged<P> {
+    backends: Vec<P>
+}
+
+impl<P> Merged<P> {
+    pub fn new(backends: Vec<P>) -> Merged<P> {
+        Merged {
+            backends
+        }
+    }
+
+    fn merge<F>(&self, search: F) -> Vec<Candidate>
+        where F: Fn(&P) -> Result<Vec<Candidate>, PredictionError> {
+        let mut result_lists = self.backends.iter()
+            .filter_map(|backend| search(backend).map_err(|err| log::error!("{}", err)).ok())
+            .map(|results| results.into_iter())
+            .collect::<Vec<_>>();
+
+        let mut seen = HashSet::new();
+        let mut merged = Vec::new();
+        loop {
+            let round = result_lists.iter_mut().filter_map(|results| results.next()).collect::<Vec<_>>();
+            if round.is_empty() {
+                break;
+            }
+            merged.extend(round.into_iter().filter(|candidate| seen.insert(candidate.commit.clone())));
+        }
+        merged
+    }
+}
+
+impl<P: WordPredictor> WordPredictor for Merged<P> {
+    fn words(&self, previous: Option<&str>, prefix: &str) -> Result<Vec<Candidate>, PredictionError> {
+        Ok(self.merge(|backend| backend.words(previous, prefix)))
+    }
+}
+
+impl<P: SymbolPredictor> SymbolPredictor for Merged<P> {
+    fn symbols(&self, context: &str) -> Result<Vec<Candidate>, PredictionError> {
+        Ok(self.merge(|backend| backend.symbols(context)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::backend::{Merged, SymbolPredictor, WordPredictor};
+    use crate::candidate::{Candidate, CandidateSource};
+    use crate::predict::PredictionError;
+
+    struct MockWords(Vec<&'static str>);
+
+    impl WordPredictor for MockWords {
+        fn words(&self, _previous: Option<&str>, prefix: &str) -> Result<Vec<Candidate>, PredictionError> {
+            Ok(self.0.iter()
+                .filter(|word| word.starts_with(prefix))
+                .map(|word| Candidate::word(String::from(*word), 1, CandidateSource::Dictionary))
+                .collect())
+        }
+    }
+
+    struct FailingWords;
+
+    impl WordPredictor for FailingWords {
+        fn words(&self, _previous: Option<&str>, _prefix: &str) -> Result<Vec<Candidate>, PredictionError> {
+            Err(PredictionError::MissingSymbol(String::from("mock"), 0))
+        }
+    }
+
+    struct MockSymbols(Vec<(&'static str, &'static str)>);
+
+    impl SymbolPredictor for MockSymbols {
+        fn symbols(&self, _context: &str) -> Result<Vec<Candidate>, PredictionError> {
+            Ok(self.0.iter().map(|(label, symbol)| Candidate {
+                display: String::from(*label),
+                commit: String::from(*symbol),
+                score: 0,
+                source: CandidateSource::Emoji,
+                description: None
+            }).collect())
+        }
+    }
+
+    fn commits(candidates: Vec<Candidate>) -> Vec<String> {
+        candidates.into_iter().map(|candidate| candidate.commit).collect()
+    }
+
+    #[test]
+    fn merge_words() {
+        let backends: Vec<Box<dyn WordPredictor>> = vec![
+            Box::new(MockWords(vec!["the", "them", "then"])),
+            Box::new(FailingWords),
+            Box::new(MockWords(vec!["the", "that", "this", "there"]))
+        ];
+        let merged = Merged::new(backends);
+        assert_eq!(commits(merged.words(None, "th").unwrap()), vec!["the", "them", "that", "then", "this", "there"]);
+        assert_eq!(commits(merged.words(None, "tha").unwrap()), vec!["that"]);
+        assert!(merged.words(None, "x").unwrap().is_empty());
+    }
+
+    #[test]
+    fn merge_symbols() {
+        let merged = Merged::new(vec![
+            MockSymbols(vec![("joy", "😂"), ("smile", "😄")]),
+            MockSymbols(vec![("laugh", "😂"), ("rofl", "🤣")])
+        ]);
+        //the first label a symbol was found under is kept
+        let labels = merged.symbols("").unwrap().into_iter()
+            .map(|candidate| (candidate.display, candidate.commit))
+            .collect::<Vec<_>>();
+        assert_eq!(labels, vec![
+            (String::from("joy"), String::from("😂")),
+            (String::from("smile"), String::from("😄")),
+            (String::from("rofl"), String::from("🤣"))
+        ]);
+    }
+}
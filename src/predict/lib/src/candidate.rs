@@ -0,0 +1,36 @@
+//Where a candidate came from, shared with C through the prediction FFI
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CandidateSource {
+    Dictionary,
+    User,
+    Snippet,
+    Emoji,
+    Math
+}
+
+//A single prediction, as shown in the lookup table
+#[derive(Debug, Clone, PartialEq)]
+pub struct Candidate {
+    //what the table shows, e.g. a shortcode for a symbol
+    pub display: String,
+    //what picking the candidate types
+    pub commit: String,
+    //higher is better, but only comparable between candidates from the same backend
+    pub score: u64,
+    pub source: CandidateSource,
+    //e.g. the official Unicode name of a symbol
+    pub description: Option<String>
+}
+
+impl Candidate {
+    pub fn word(word: String, score: u64, source: CandidateSource) -> Candidate {
+        Candidate {
+            display: word.clone(),
+            commit: word,
+            score,
+            source,
+            description: None
+        }
+    }
+}
@@ -0,0 +1,93 @@
+use std::fmt;
+use crate::codepoint::CodepointError::*;
+
+//Characters typed by their hexadecimal code point, as in U+E9 for é
+pub const PREFIX: &str = "U+";
+//U+10FFFF is the highest code point
+pub const MAX_DIGITS: usize = 6;
+
+#[derive(Debug, PartialEq)]
+pub enum CodepointError {
+    Empty,
+    InvalidHex(String),
+    //the halves of UTF-16 surrogate pairs are not characters on their own
+    Surrogate(u32),
+    OutOfRange(u32),
+    Control(u32)
+}
+
+impl fmt::Display for CodepointError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Empty => write!(f, "no code point"),
+            InvalidHex(hex) => write!(f, "{} is not hexadecimal", hex),
+            Surrogate(_) => write!(f, "surrogates can't be typed"),
+            OutOfRange(_) => write!(f, "beyond U+10FFFF"),
+            Control(_) => write!(f, "control characters can't be typed"),
+        }
+    }
+}
+
+//"u+" or "U+" typed in the symbol table switches to code point entry
+pub fn is_prefix(input: &str) -> bool {
+    input.eq_ignore_ascii_case(PREFIX)
+}
+
+pub fn parse_codepoint(hex: &str) -> Result<char, CodepointError> {
+    if hex.is_empty() {
+        return Err(Empty);
+    }
+    //from_str_radix would also accept a leading +
+    if !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(InvalidHex(String::from(hex)));
+    }
+    let codepoint = u32::from_str_radix(hex, 16).map_err(|_err| InvalidHex(String::from(hex)))?;
+    match char::from_u32(codepoint) {
+        Some(c) if c.is_control() => Err(Control(codepoint)),
+        Some(c) => Ok(c),
+        None if (0xD800..=0xDFFF).contains(&codepoint) => Err(Surrogate(codepoint)),
+        None => Err(OutOfRange(codepoint))
+    }
+}
+
+//what the auxiliary text shows while typing, e.g. "U+3B1 α greek small letter alpha"
+pub fn preview<F: Fn(char) -> Option<String>>(hex: &str, name: F) -> String {
+    let typed = format!("{}{}", PREFIX, hex.to_ascii_uppercase());
+    match parse_codepoint(hex) {
+        Ok(c) => match name(c) {
+            Some(name) => format!("{} {} {}", typed, c, name),
+            None => format!("{} {}", typed, c)
+        },
+        Err(Empty) => typed,
+        Err(err) => format!("{} ({})", typed, err)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::codepoint::{is_prefix, parse_codepoint, preview};
+    use crate::codepoint::CodepointError::*;
+
+    #[test]
+    fn codepoints() {
+        assert_eq!(parse_codepoint("e9"), Ok('é'));
+        assert_eq!(parse_codepoint("1F600"), Ok('😀'));
+        assert_eq!(parse_codepoint("10FFFF"), Ok('\u{10FFFF}'));
+        assert_eq!(parse_codepoint(""), Err(Empty));
+        assert_eq!(parse_codepoint("+41"), Err(InvalidHex(String::from("+41"))));
+        assert_eq!(parse_codepoint("D800"), Err(Surrogate(0xD800)));
+        assert_eq!(parse_codepoint("dfff"), Err(Surrogate(0xDFFF)));
+        assert_eq!(parse_codepoint("110000"), Err(OutOfRange(0x110000)));
+        assert_eq!(parse_codepoint("0"), Err(Control(0)));
+
+        assert!(is_prefix("u+"));
+        assert!(is_prefix("U+"));
+        assert!(!is_prefix("u"));
+
+        let name = |c: char| if c == 'α' { Some(String::from("greek small letter alpha")) } else { None };
+        assert_eq!(preview("3b1", name), "U+3B1 α greek small letter alpha");
+        assert_eq!(preview("e9", name), "U+E9 é");
+        assert_eq!(preview("", name), "U+");
+        assert_eq!(preview("d800", name), "U+D800 (surrogates can't be typed)");
+    }
+}
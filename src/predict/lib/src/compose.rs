@@ -0,0 +1,64 @@
+use unicode_normalization::UnicodeNormalization;
+
+//Accented letters typed as the letter followed by a mnemonic for the accent, e.g. e' for é or Ao for Å
+fn combining_mark(mnemonic: char) -> Option<char> {
+    match mnemonic {
+        '\'' => Some('\u{0301}'), //acute
+        '`' => Some('\u{0300}'), //grave
+        '^' => Some('\u{0302}'), //circumflex
+        ':' | '"' => Some('\u{0308}'), //diaeresis
+        '~' => Some('\u{0303}'), //tilde
+        ',' => Some('\u{0327}'), //cedilla
+        'o' => Some('\u{030A}'), //ring above
+        '-' | '=' => Some('\u{0304}'), //macron
+        'v' => Some('\u{030C}'), //caron
+        'u' => Some('\u{0306}'), //breve
+        '.' => Some('\u{0307}'), //dot above
+        ';' => Some('\u{0328}'), //ogonek
+        _ => None
+    }
+}
+
+//the precomposed character, if Unicode has one for the letter with that accent
+pub fn compose(letter: char, mnemonic: char) -> Option<char> {
+    let mark = combining_mark(mnemonic)?;
+    let pair = [letter, mark];
+    let mut composed = pair.iter().copied().nfc();
+    match (composed.next(), composed.next()) {
+        (Some(c), None) => Some(c),
+        _ => None
+    }
+}
+
+//what the auxiliary text shows, including the last accent that couldn't be applied
+pub fn preview(letter: Option<char>, rejected: Option<char>) -> String {
+    match (letter, rejected) {
+        (Some(letter), Some(mnemonic)) => format!("compose {}: no {}{} accent", letter, letter, mnemonic),
+        (Some(letter), None) => format!("compose {}", letter),
+        (None, _) => String::from("compose")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::compose::{compose, preview};
+
+    #[test]
+    fn accents() {
+        assert_eq!(compose('e', '\''), Some('é'));
+        assert_eq!(compose('a', ':'), Some('ä'));
+        assert_eq!(compose('o', '^'), Some('ô'));
+        assert_eq!(compose('A', 'o'), Some('Å'));
+        assert_eq!(compose('o', '"'), Some('ö'));
+        assert_eq!(compose('c', ','), Some('ç'));
+        assert_eq!(compose('n', '~'), Some('ñ'));
+        assert_eq!(compose('s', 'v'), Some('š'));
+        //no precomposed q with an acute
+        assert_eq!(compose('q', '\''), None);
+        assert_eq!(compose('e', 'x'), None);
+
+        assert_eq!(preview(None, None), "compose");
+        assert_eq!(preview(Some('q'), None), "compose q");
+        assert_eq!(preview(Some('q'), Some('\'')), "compose q: no q' accent");
+    }
+}
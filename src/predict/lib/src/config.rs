@@ -0,0 +1,155 @@
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use lazy_static::lazy_static;
+use serde::Deserialize;
+use crate::config::ConfigError::*;
+use crate::emoji::SkinTone;
+use crate::user_config_dir;
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct DictionaryConfig {
+    pub name: String,
+    #[serde(default = "DictionaryConfig::default_weight")]
+    pub weight: f64
+}
+
+impl DictionaryConfig {
+    //the dictionary built from hunspell_US.txt, which is also compiled into the engine
+    pub const DEFAULT_NAME: &'static str = "en_US";
+
+    fn default_weight() -> f64 {
+        1.0
+    }
+}
+
+//User settings read from config.toml; anything missing from the file keeps its default
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub dictionaries: Vec<DictionaryConfig>,
+    pub max_edit_distance: u32,
+    //applied to emoji picked straight from the symbol table
+    pub skin_tone: Option<SkinTone>,
+    //offer corrections when a word that isn't in any dictionary is finished
+    pub spell_check: bool,
+    //replace such a word straight away when one correction is far more likely than any other
+    pub autocorrect: bool,
+    //remember words that aren't in any dictionary, unless a correction was picked for them
+    pub learn_unknown_words: bool,
+    //pressed with ctrl, starts composing an accented letter. Unset by default, since applications
+    //already use most ctrl+letter combinations (ctrl+d ends input in a terminal)
+    pub compose_hotkey: Option<char>
+}
+
+impl Default for Config {
+    fn default() -> Config {
+        Config {
+            dictionaries: vec![DictionaryConfig {
+                name: String::from(DictionaryConfig::DEFAULT_NAME),
+                weight: DictionaryConfig::default_weight()
+            }],
+            max_edit_distance: 2,
+            skin_tone: None,
+            spell_check: false,
+            autocorrect: false,
+            learn_unknown_words: true,
+            compose_hotkey: None
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum ConfigError {
+    IoError(io::Error),
+    ParseError(toml::de::Error)
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            IoError(err) => write!(f, "Failed to read config: {}", err),
+            ParseError(err) => write!(f, "Invalid config: {}", err),
+        }
+    }
+}
+
+impl Config {
+    const FILENAME: &'static str = "config.toml";
+
+    pub fn parse(text: &str) -> Result<Config, ConfigError> {
+        toml::from_str(text).map_err(ParseError)
+    }
+
+    pub fn load(path: &Path) -> Result<Config, ConfigError> {
+        match fs::read_to_string(path) {
+            Ok(text) => Config::parse(text.as_str()),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(Config::default()),
+            Err(err) => Err(IoError(err))
+        }
+    }
+
+    fn path() -> Option<PathBuf> {
+        user_config_dir().ok().map(|dir| dir.join(Config::FILENAME))
+    }
+}
+
+lazy_static! {
+    static ref CONFIG: Config = match Config::path() {
+        Some(path) => Config::load(path.as_path()).unwrap_or_else(|err| {
+            log::error!("{}, using the default configuration", err);
+            Config::default()
+        }),
+        None => {
+            log::error!("Could not find config directory, using the default configuration");
+            Config::default()
+        }
+    };
+}
+
+pub fn config() -> &'static Config {
+    &CONFIG
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::{Config, DictionaryConfig};
+    use crate::emoji::SkinTone;
+
+    #[test]
+    fn parse() {
+        assert_eq!(Config::parse("").unwrap(), Config::default());
+
+        let config = Config::parse(r#"
+            max_edit_distance = 1
+            skin_tone = "medium-dark"
+            spell_check = true
+            autocorrect = true
+            learn_unknown_words = false
+            compose_hotkey = ";"
+
+            [[dictionaries]]
+            name = "en_GB"
+
+            [[dictionaries]]
+            name = "jargon"
+            weight = 2.5
+        "#).unwrap();
+        assert_eq!(config.max_edit_distance, 1);
+        assert_eq!(config.skin_tone, Some(SkinTone::MediumDark));
+        assert!(config.spell_check);
+        assert!(config.autocorrect);
+        assert!(!config.learn_unknown_words);
+        assert_eq!(config.compose_hotkey, Some(';'));
+        assert_eq!(config.dictionaries, vec![
+            DictionaryConfig { name: String::from("en_GB"), weight: 1.0 },
+            DictionaryConfig { name: String::from("jargon"), weight: 2.5 }
+        ]);
+
+        assert!(Config::parse("max_edit_distnace = 1").is_err());
+        assert!(Config::parse("skin_tone = \"purple\"").is_err());
+        assert!(Config::parse("compose_hotkey = \"ctrl+d\"").is_err());
+    }
+}
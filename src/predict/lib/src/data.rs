@@ -0,0 +1,38 @@
+use std::path::{Path, PathBuf};
+use crate::{user_data_dir, DATA_DIRNAME};
+
+//Bytes backing the prediction data, either compiled into the engine or read from a data pack on disk
+pub enum Data {
+    Embedded(&'static [u8]),
+    Owned(Vec<u8>)
+}
+
+impl AsRef<[u8]> for Data {
+    fn as_ref(&self) -> &[u8] {
+        match self {
+            Data::Embedded(bytes) => bytes,
+            Data::Owned(bytes) => bytes.as_slice()
+        }
+    }
+}
+
+//Directories searched for data files, most specific first: the user's own data dir, then the
+//system wide ones from XDG_DATA_DIRS
+pub fn data_dirs() -> Vec<PathBuf> {
+    let system_dirs = std::env::var("XDG_DATA_DIRS")
+        .ok()
+        .filter(|dirs| !dirs.is_empty())
+        .unwrap_or_else(|| String::from("/usr/local/share:/usr/share"));
+
+    user_data_dir().into_iter()
+        .chain(system_dirs.split(':')
+            .filter(|dir| !dir.is_empty())
+            .map(|dir| Path::new(dir).join(DATA_DIRNAME)))
+        .collect()
+}
+
+pub fn find_data_file(dirs: &[PathBuf], filename: &str) -> Option<PathBuf> {
+    dirs.iter()
+        .map(|dir| dir.join(filename))
+        .find(|path| path.is_file())
+}
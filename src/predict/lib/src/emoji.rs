@@ -0,0 +1,114 @@
+use serde::Deserialize;
+
+//Emoji_Modifier_Base ranges from https://www.unicode.org/Public/15.0.0/ucd/emoji/emoji-data.txt
+const MODIFIER_BASES: [(u32, u32); 40] = [
+    (0x261D, 0x261D), (0x26F9, 0x26F9), (0x270A, 0x270D), (0x1F385, 0x1F385), (0x1F3C2, 0x1F3C4),
+    (0x1F3C7, 0x1F3C7), (0x1F3CA, 0x1F3CC), (0x1F442, 0x1F443), (0x1F446, 0x1F450), (0x1F466, 0x1F478),
+    (0x1F47C, 0x1F47C), (0x1F481, 0x1F483), (0x1F485, 0x1F487), (0x1F48F, 0x1F48F), (0x1F491, 0x1F491),
+    (0x1F4AA, 0x1F4AA), (0x1F574, 0x1F575), (0x1F57A, 0x1F57A), (0x1F590, 0x1F590), (0x1F595, 0x1F596),
+    (0x1F645, 0x1F647), (0x1F64B, 0x1F64F), (0x1F6A3, 0x1F6A3), (0x1F6B4, 0x1F6B6), (0x1F6C0, 0x1F6C0),
+    (0x1F6CC, 0x1F6CC), (0x1F90C, 0x1F90C), (0x1F90F, 0x1F90F), (0x1F918, 0x1F91F), (0x1F926, 0x1F926),
+    (0x1F930, 0x1F939), (0x1F93C, 0x1F93E), (0x1F977, 0x1F977), (0x1F9B5, 0x1F9B6), (0x1F9B8, 0x1F9B9),
+    (0x1F9BB, 0x1F9BB), (0x1F9CD, 0x1F9CF), (0x1F9D1, 0x1F9DD), (0x1FAC3, 0x1FAC5), (0x1FAF0, 0x1FAF8),
+];
+
+const VARIATION_SELECTOR_16: char = '\u{FE0F}';
+
+//Fitzpatrick skin tone modifiers, U+1F3FB to U+1F3FF
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum SkinTone {
+    Light,
+    MediumLight,
+    Medium,
+    MediumDark,
+    Dark
+}
+
+impl SkinTone {
+    pub const ALL: [SkinTone; 5] = [SkinTone::Light, SkinTone::MediumLight, SkinTone::Medium,
+                                    SkinTone::MediumDark, SkinTone::Dark];
+
+    fn modifier(self) -> char {
+        match self {
+            SkinTone::Light => '\u{1F3FB}',
+            SkinTone::MediumLight => '\u{1F3FC}',
+            SkinTone::Medium => '\u{1F3FD}',
+            SkinTone::MediumDark => '\u{1F3FE}',
+            SkinTone::Dark => '\u{1F3FF}',
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            SkinTone::Light => "light",
+            SkinTone::MediumLight => "medium-light",
+            SkinTone::Medium => "medium",
+            SkinTone::MediumDark => "medium-dark",
+            SkinTone::Dark => "dark",
+        }
+    }
+
+    fn is_modifier(c: char) -> bool {
+        ('\u{1F3FB}'..='\u{1F3FF}').contains(&c)
+    }
+}
+
+fn is_modifier_base(c: char) -> bool {
+    let codepoint = c as u32;
+    MODIFIER_BASES.iter().any(|(start, end)| (*start..=*end).contains(&codepoint))
+}
+
+pub fn supports_skin_tone(symbol: &str) -> bool {
+    symbol.chars().any(is_modifier_base)
+}
+
+//Applies the tone to every person in the emoji, replacing any tone it already had. The modifier
+//takes the place of a variation selector following the base, as in the fully qualified sequences.
+pub fn with_skin_tone(symbol: &str, tone: SkinTone) -> String {
+    let mut toned = String::with_capacity(symbol.len() + 4);
+    let mut chars = symbol.chars().filter(|c| !SkinTone::is_modifier(*c)).peekable();
+    while let Some(c) = chars.next() {
+        toned.push(c);
+        if is_modifier_base(c) {
+            toned.push(tone.modifier());
+            if chars.peek() == Some(&VARIATION_SELECTOR_16) {
+                chars.next();
+            }
+        }
+    }
+    toned
+}
+
+//The emoji as-is followed by each of its skin tones, labelled with the tone name
+pub fn skin_tone_variants(shortcode: &str, symbol: &str) -> Vec<(String, String)> {
+    let mut variants = vec![(String::from(shortcode), String::from(symbol))];
+    if supports_skin_tone(symbol) {
+        variants.extend(SkinTone::ALL.iter().map(|tone| {
+            (format!("{} {}", shortcode, tone.name()), with_skin_tone(symbol, *tone))
+        }));
+    }
+    variants
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::emoji::{skin_tone_variants, supports_skin_tone, with_skin_tone, SkinTone};
+
+    #[test]
+    fn skin_tones() {
+        assert!(supports_skin_tone("👍"));
+        assert!(!supports_skin_tone("🚀"));
+        assert_eq!(with_skin_tone("👍", SkinTone::Medium), "👍🏽");
+        assert_eq!(with_skin_tone("👍🏻", SkinTone::Dark), "👍🏿");
+        //the variation selector is replaced, and the gender sign after the joiner is untouched
+        assert_eq!(with_skin_tone("🏃\u{200D}♀\u{FE0F}", SkinTone::Light), "🏃🏻\u{200D}♀\u{FE0F}");
+        assert_eq!(with_skin_tone("☝\u{FE0F}", SkinTone::MediumDark), "☝🏾");
+        assert_eq!(with_skin_tone("🚀", SkinTone::Light), "🚀");
+
+        assert_eq!(skin_tone_variants("rocket", "🚀").len(), 1);
+        let variants = skin_tone_variants("+1", "👍");
+        assert_eq!(variants.len(), 6);
+        assert_eq!(variants[2], (String::from("+1 medium-light"), String::from("👍🏼")));
+    }
+}
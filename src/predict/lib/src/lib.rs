@@ -1,5 +1,18 @@
 #![allow(non_upper_case_globals)]
-mod predict;
+mod automaton;
+mod backend;
+pub mod candidate;
+mod codepoint;
+mod compose;
+pub mod config;
+mod data;
+mod emoji;
+mod persistent;
+pub mod predict;
+mod script;
+mod snippets;
+mod symbol_history;
+pub mod user_dictionary;
 
 use std::ffi::{CString, NulError, CStr};
 use std::os::raw::{c_char, c_int};
@@ -7,12 +20,20 @@ use log::{LevelFilter};
 use log4rs::encode::pattern::PatternEncoder;
 use log4rs::config::{Appender, Config, Root};
 
-use crate::predict::PREDICTOR;
-use ibus::{IBusEEIEngine, gboolean, GBOOL_FALSE, ibus_engine_update_lookup_table, IBusEngine, GBOOL_TRUE, ibus_engine_hide_lookup_table, guint, IBusModifierType_IBUS_CONTROL_MASK, IBUS_e, IBUS_w, IBUS_asciitilde, IBUS_space, IBUS_Return, IBUS_BackSpace, IBUS_Escape, IBUS_Page_Down, IBUS_Page_Up, ibus_engine_commit_text, ibus_text_new_from_unichar, ibus_text_new_from_string, gchar, ibus_lookup_table_clear, ibus_lookup_table_append_candidate, IBusText, ibus_engine_update_auxiliary_text, IBUS_Up, IBUS_Down, ibus_lookup_table_get_cursor_pos, IBusLookupTable, ibus_lookup_table_get_label, ibus_lookup_table_cursor_up, ibus_lookup_table_cursor_down, ibus_engine_hide_auxiliary_text, ibus_lookup_table_set_label, ibus_lookup_table_page_down, ibus_lookup_table_page_up, ibus_lookup_table_get_number_of_candidates, ibus_text_new_from_static_string, ibus_lookup_table_get_cursor_in_page, gunichar, IBusModifierType_IBUS_SHIFT_MASK, ibus_lookup_table_get_candidate, ibus_engine_update_preedit_text, ibus_engine_hide_preedit_text, ibus_text_get_length, ibus_text_append_attribute, IBusAttrType_IBUS_ATTR_TYPE_UNDERLINE, IBusAttrUnderline_IBUS_ATTR_UNDERLINE_SINGLE, gint, IBUS_Right, IBUS_Left, IBusEngineClass};
+use crate::backend::{Merged, SymbolPredictor, WordPredictor};
+use crate::candidate::{Candidate, CandidateSource};
+use crate::codepoint::{parse_codepoint, MAX_DIGITS};
+use crate::config::config;
+use crate::predict::{predictor, AutocorrectConfidence, Completions, NextWords, PredictionError, Predictor, SymbolSearch};
+use crate::script::Script;
+use crate::snippets::{snippets, SnippetSearch, Snippets};
+use crate::symbol_history::symbol_history;
+use crate::user_dictionary::user_dictionary;
+use ibus::{IBusEEIEngine, gboolean, GBOOL_FALSE, ibus_engine_update_lookup_table, IBusEngine, GBOOL_TRUE, ibus_engine_hide_lookup_table, guint, IBusModifierType_IBUS_CONTROL_MASK, IBUS_e, IBUS_w, IBUS_space, IBUS_Return, IBUS_BackSpace, IBUS_Escape, IBUS_Page_Down, IBUS_Page_Up, ibus_engine_commit_text, ibus_text_new_from_unichar, ibus_text_new_from_string, gchar, ibus_lookup_table_clear, ibus_lookup_table_append_candidate, IBusText, ibus_engine_update_auxiliary_text, IBUS_Up, IBUS_Down, ibus_lookup_table_get_cursor_pos, IBusLookupTable, ibus_lookup_table_get_label, ibus_lookup_table_cursor_up, ibus_lookup_table_cursor_down, ibus_engine_hide_auxiliary_text, ibus_lookup_table_set_label, ibus_lookup_table_page_down, ibus_lookup_table_page_up, ibus_lookup_table_get_number_of_candidates, ibus_text_new_from_static_string, ibus_lookup_table_get_cursor_in_page, gunichar, IBusModifierType_IBUS_SHIFT_MASK, ibus_engine_update_preedit_text, ibus_engine_hide_preedit_text, ibus_text_get_length, ibus_text_append_attribute, IBusAttrType_IBUS_ATTR_TYPE_UNDERLINE, IBusAttrUnderline_IBUS_ATTR_UNDERLINE_SINGLE, gint, IBUS_Right, IBUS_Left, IBusEngineClass, IBUS_Tab, ibus_keyval_to_unicode, ibus_engine_delete_surrounding_text, IBusModifierType_IBUS_RELEASE_MASK, ibus_engine_get_content_type, IBusInputPurpose_IBUS_INPUT_PURPOSE_FREE_FORM, IBusInputPurpose_IBUS_INPUT_PURPOSE_PASSWORD, IBusInputPurpose_IBUS_INPUT_PURPOSE_PIN, IBusCapabilite_IBUS_CAP_SURROUNDING_TEXT, ibus_engine_get_surrounding_text, ibus_text_get_text, IBUS_Shift_L, IBUS_Hyper_R, IBUS_ISO_Lock, IBUS_ISO_Last_Group_Lock};
 use std::cmp::min;
 use lazy_static::lazy_static;
 use InputMode::*;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use log4rs::append::rolling_file::policy::compound::roll::fixed_window::FixedWindowRoller;
 use log4rs::append::rolling_file::policy::compound::trigger::size::SizeTrigger;
 use log4rs::append::rolling_file::policy::compound::CompoundPolicy;
@@ -27,16 +48,37 @@ lazy_static! {
 enum InputMode {
     Normal,
     SymbolTable,
-    WordTable
+    WordTable,
+    //corrections for the word just finished, which isn't in any dictionary
+    CorrectionTable,
+    //typing a character by its hexadecimal code point
+    CodepointEntry,
+    //typing superscript or subscript text, e.g. the ² in x²
+    ScriptEntry(Script),
+    //typing a letter and then a mnemonic for its accent, e.g. e' for é
+    ComposeEntry
 }
 
 pub struct EngineCore {
     table_visible: bool,
     word_buffer: String,
+    last_word: Option<String>,
     input_mode: InputMode,
+    //what is shown in the lookup table, in table order
+    candidates: Vec<Candidate>,
     symbol_preedit: String,
     symbol_label_vec: Vec<CString>,
     symbol_last_page: guint,
+    symbol_variants_visible: bool,
+    misspelled_word: String,
+    //the word as typed and what it was autocorrected to, until the next key press
+    last_autocorrection: Option<(String, String)>,
+    //the hex digits typed so far in code point entry
+    codepoint_preedit: String,
+    //the converted superscript or subscript text, committed as a whole
+    script_preedit: String,
+    //the letter typed in accent composition, waiting for its accent
+    compose_letter: Option<char>,
     parent_engine: *mut IBusEEIEngine,
     parent_engine_class: *mut IBusEngineClass,
 }
@@ -46,15 +88,98 @@ pub unsafe extern "C" fn new_engine_core(parent_engine: *mut IBusEEIEngine, pare
     Box::into_raw(Box::new(EngineCore {
         table_visible: false,
         word_buffer: String::new(),
+        last_word: None,
         input_mode: InputMode::Normal,
+        candidates: Vec::new(),
         symbol_preedit: String::new(),
         symbol_label_vec: Vec::new(),
         symbol_last_page: 0,
+        symbol_variants_visible: false,
+        misspelled_word: String::new(),
+        last_autocorrection: None,
+        codepoint_preedit: String::new(),
+        script_preedit: String::new(),
+        compose_letter: None,
         parent_engine: parent_engine,
         parent_engine_class: parent_engine_class
     }))
 }
 
+//the character a key types, if any, e.g. é for IBUS_eacute
+unsafe fn keyval_char(keyval: guint) -> Option<char> {
+    std::char::from_u32(ibus_keyval_to_unicode(keyval)).filter(|c| *c != '\0' && !c.is_control())
+}
+
+//apostrophes and hyphens only belong to a word inside it, as in "doesn't" or "well-known"; any other
+//punctuation ends the word
+fn is_word_char(c: char, word_buffer: &str) -> bool {
+    c.is_alphanumeric() || ((c == '\'' || c == '-') && !word_buffer.is_empty())
+}
+
+//what committing a completion does to the word typed so far: how many characters before the cursor to
+//delete, and the text to commit. A completion that doesn't just extend what was typed, like "definitely"
+//for "definat" or "London" for "lon", replaces it.
+fn word_edit(typed: &str, word: &str) -> (usize, String) {
+    match word.strip_prefix(typed) {
+        Some(rest) => (0, String::from(rest)),
+        None => (typed.chars().count(), String::from(word))
+    }
+}
+
+//a word as typed without the trailing ' or - that may follow it, e.g. "freinds" for "freinds'"
+fn trim_word(typed: &str) -> &str {
+    typed.trim_end_matches(&['\'', '-'][..])
+}
+
+//a correction for the trimmed word, with the punctuation that followed the typed word put back
+fn with_trailing_punctuation(correction: &str, typed: &str) -> String {
+    format!("{}{}", correction, &typed[trim_word(typed).len()..])
+}
+
+//what replacing a word and the space after it does: how many characters before the cursor to delete,
+//and the text to commit in their place
+fn replacement_edit(typed: &str, replacement: &str) -> (usize, String) {
+    (typed.chars().count() + 1, format!("{} ", replacement))
+}
+
+//whether the text before the cursor ends with what was typed, with nothing selected. IBus gives the
+//cursor and anchor positions in characters.
+fn typed_before_cursor(surrounding: &str, cursor_pos: usize, anchor_pos: usize, typed: &str) -> bool {
+    cursor_pos == anchor_pos && surrounding.chars().take(cursor_pos).collect::<String>().ends_with(typed)
+}
+
+//keys that type nothing, like Home, Delete or Tab, may move the cursor or change the text around it,
+//so what was typed before them is no longer known to be before the cursor. Shift, Control, Alt and
+//the like only change what the next key does.
+unsafe fn forgets_word(keyval: guint) -> bool {
+    let is_modifier = (IBUS_Shift_L..=IBUS_Hyper_R).contains(&keyval) || (IBUS_ISO_Lock..=IBUS_ISO_Last_Group_Lock).contains(&keyval);
+    keyval_char(keyval).is_none() && !is_modifier
+}
+
+//words likely to follow the previous one are mixed in with the completions, and are all
+//there is to suggest before anything is typed
+fn word_search(previous: Option<&str>, prefix: &str) -> Result<Vec<Candidate>, PredictionError> {
+    let predictor = predictor();
+    let user_dictionary = user_dictionary();
+    let backends: Vec<Box<dyn WordPredictor>> = vec![
+        Box::new(NextWords(&predictor)),
+        Box::new(Completions { predictor: &predictor, user_dictionary: &user_dictionary })
+    ];
+    let merged = Merged::new(backends);
+    merged.words(previous, prefix)
+}
+
+fn symbol_search(query: &str) -> Result<Vec<Candidate>, PredictionError> {
+    let predictor = predictor();
+    let history = symbol_history();
+    let backends: Vec<Box<dyn SymbolPredictor>> = vec![
+        Box::new(SnippetSearch(snippets())),
+        Box::new(SymbolSearch { predictor: &predictor, history: &history })
+    ];
+    let merged = Merged::new(backends);
+    merged.symbols(query)
+}
+
 unsafe fn into_ibus_string(input: String) -> Result<*mut IBusText, NulError> {
     CString::new(input.into_bytes()).map(|cstr| ibus_text_new_from_string(cstr.into_raw() as *const gchar))
 }
@@ -92,14 +217,56 @@ impl EngineCore {
             }
             WordTable => {
                 self.word_buffer.clear();
+                self.last_word = None;
                 self.word_table_disable()
             }
+            CorrectionTable => {
+                self.correction_dismiss()
+            }
+            CodepointEntry => {
+                self.codepoint_disable()
+            }
+            ScriptEntry(_) => {
+                self.script_disable()
+            }
+            ComposeEntry => {
+                self.compose_disable()
+            }
             Normal => {
+                self.forget_word();
                 GBOOL_FALSE
             }
         }
     }
 
+    //the cursor may have moved, so neither the word being typed nor the last autocorrection is still
+    //right before it
+    fn forget_word(&mut self) {
+        self.word_buffer.clear();
+        self.last_word = None;
+        self.last_autocorrection = None;
+    }
+
+    //the engine doesn't see every cursor move, like a click elsewhere in the text, so it checks that
+    //text it typed is still right before the cursor before deleting it
+    unsafe fn is_before_cursor(&self, typed: &str) -> bool {
+        let mut text: *mut IBusText = std::ptr::null_mut();
+        let mut cursor_pos: guint = 0;
+        let mut anchor_pos: guint = 0;
+        ibus_engine_get_surrounding_text(self.parent_engine_as_ibus_engine(), &mut text, &mut cursor_pos, &mut anchor_pos);
+        if text.is_null() || ibus_text_get_text(text).is_null() {
+            log::warn!("No surrounding text, not replacing {}", typed);
+            return false;
+        }
+
+        let surrounding = CStr::from_ptr(ibus_text_get_text(text)).to_string_lossy();
+        let found = typed_before_cursor(surrounding.as_ref(), cursor_pos as usize, anchor_pos as usize, typed);
+        if !found {
+            log::warn!("{} is no longer before the cursor, not replacing it", typed);
+        }
+        found
+    }
+
     unsafe fn commit_from_table(&mut self, idx: Option<guint>) -> gboolean {
         let ret = match self.input_mode {
             SymbolTable => {
@@ -110,6 +277,19 @@ impl EngineCore {
                 self.word_commit(idx);
                 GBOOL_TRUE
             }
+            CorrectionTable => {
+                self.correction_commit(idx);
+                GBOOL_TRUE
+            }
+            CodepointEntry => {
+                self.codepoint_commit()
+            }
+            ScriptEntry(_) => {
+                self.script_commit()
+            }
+            ComposeEntry => {
+                GBOOL_FALSE
+            }
             Normal => {
                 GBOOL_FALSE
             }
@@ -122,6 +302,20 @@ impl EngineCore {
         self.parent_engine as *mut IBusEngine
     }
 
+    //nothing typed into password or PIN fields is learned or written to the user dictionary
+    unsafe fn is_private_field(&self) -> bool {
+        let mut purpose = IBusInputPurpose_IBUS_INPUT_PURPOSE_FREE_FORM;
+        let mut hints = 0;
+        ibus_engine_get_content_type(self.parent_engine_as_ibus_engine(), &mut purpose, &mut hints);
+        purpose == IBusInputPurpose_IBUS_INPUT_PURPOSE_PASSWORD || purpose == IBusInputPurpose_IBUS_INPUT_PURPOSE_PIN
+    }
+
+    //completions that change what was typed, autocorrect and corrections delete text before the cursor,
+    //which the client has to support
+    unsafe fn can_replace(&self) -> bool {
+        (*self.parent_engine_as_ibus_engine()).client_capabilities & IBusCapabilite_IBUS_CAP_SURROUNDING_TEXT != 0
+    }
+
     unsafe fn get_table(&self) -> *mut IBusLookupTable {
         (*self.parent_engine).table
     }
@@ -130,9 +324,8 @@ impl EngineCore {
         ((*(engine as *mut IBusEEIEngine)).engine_core as *mut EngineCore).as_mut()
     }
 
-    unsafe fn commit_char(&mut self, keyval: guint) {
-        self.word_buffer.push((keyval as u8) as char);
-        ibus_engine_commit_text(self.parent_engine_as_ibus_engine(), ibus_text_new_from_unichar(keyval as gunichar));
+    unsafe fn commit_char(&mut self, c: char) {
+        ibus_engine_commit_text(self.parent_engine_as_ibus_engine(), ibus_text_new_from_unichar(c as gunichar));
     }
 
     unsafe fn commit_text(&mut self, text: *mut IBusText) {
@@ -176,17 +369,25 @@ impl EngineCore {
                                                 len,GBOOL_TRUE);
             }
             WordTable => {
+                //the rest of the word for a plain completion, or the whole word if it replaces what was typed
                 let idx = ibus_lookup_table_get_cursor_pos(self.get_table());
-                let candidate = ibus_lookup_table_get_candidate(self.get_table(), idx);
-                self.get_word_remainder(candidate).map(|remainder| {
-                    let len = ibus_text_get_length(remainder);
-                    ibus_text_append_attribute(remainder, IBusAttrType_IBUS_ATTR_TYPE_UNDERLINE,
-                                               IBusAttrUnderline_IBUS_ATTR_UNDERLINE_SINGLE, 0, len as gint);
-                    ibus_engine_update_preedit_text(self.parent_engine_as_ibus_engine(), remainder,
-                                                    len, GBOOL_TRUE);
-                });
+                let preview = self.candidates.get(idx as usize)
+                    .map(|candidate| word_edit(self.word_buffer.as_str(), candidate.commit.as_str()).1);
+                match preview.map(|preview| into_ibus_string(preview)) {
+                    Some(Ok(preview)) => {
+                        let len = ibus_text_get_length(preview);
+                        ibus_text_append_attribute(preview, IBusAttrType_IBUS_ATTR_TYPE_UNDERLINE,
+                                                   IBusAttrUnderline_IBUS_ATTR_UNDERLINE_SINGLE, 0, len as gint);
+                        ibus_engine_update_preedit_text(self.parent_engine_as_ibus_engine(), preview,
+                                                        len, GBOOL_TRUE);
+                    }
+                    Some(Err(err)) => {
+                        log::error!("Failed string conversion for word preedit: {}", err);
+                    }
+                    None => {}
+                }
             }
-            Normal => {}
+            CorrectionTable | CodepointEntry | ScriptEntry(_) | ComposeEntry | Normal => {}
         }
     }
 
@@ -195,14 +396,20 @@ impl EngineCore {
      */
 
     unsafe fn word_table_enable(&mut self) -> gboolean {
-        if self.table_visible || self.word_buffer.is_empty() {
-            //not an error if this is called while word buffer is empty, so don't log
+        if self.table_visible || (self.word_buffer.is_empty() && self.last_word.is_none()) {
+            //not an error if this is called with nothing to complete or follow, so don't log
+            return GBOOL_FALSE
+        }
+
+        //nothing to suggest, so let the key through rather than show an empty table
+        let candidates = self.word_candidates();
+        if candidates.is_empty() {
             return GBOOL_FALSE
         }
 
         self.input_mode = WordTable;
         self.table_visible = true;
-        self.word_table_update();
+        self.word_table_show(candidates);
         GBOOL_TRUE
     }
 
@@ -224,36 +431,54 @@ impl EngineCore {
             log::error!("Word table update called while table invisible or input mode is not word");
             return;
         }
-        else if self.word_buffer.is_empty() {
+        else if self.word_buffer.is_empty() && self.last_word.is_none() {
             self.word_table_disable();
             return;
         }
 
-        let search_result  = PREDICTOR.word(self.word_buffer.as_str());
-        match search_result {
+        let candidates = self.word_candidates();
+        self.word_table_show(candidates);
+    }
+
+    unsafe fn word_candidates(&self) -> Vec<Candidate> {
+        match word_search(self.last_word.as_deref(), self.word_buffer.as_str()) {
             Ok(candidates) => {
                 log::info!("Word search for {} and got {:?}", self.word_buffer, candidates);
-                let table = self.get_table();
-                ibus_lookup_table_clear(table);
-                for word in candidates {
-                    match into_ibus_string(word) {
-                        Ok(ibus_text) => {
-                            ibus_lookup_table_append_candidate(table, ibus_text)
-                        }
-                        Err(err) => {
-                            log::error!("Failed string conversion for word lookup: {}", err);
-                        }
-                    }
+                if self.can_replace() {
+                    candidates
+                } else {
+                    //without deleting what was typed, only completions that extend it can be committed
+                    candidates.into_iter()
+                        .filter(|candidate| word_edit(self.word_buffer.as_str(), candidate.commit.as_str()).0 == 0)
+                        .collect()
                 }
-                ibus_engine_update_lookup_table(self.parent_engine_as_ibus_engine(), table, GBOOL_TRUE);
-                self.update_preedit();
             }
             Err(err) => {
                 log::error!("{}", err);
+                Vec::new()
             }
         }
     }
 
+    unsafe fn word_table_show(&mut self, candidates: Vec<Candidate>) {
+        let table = self.get_table();
+        ibus_lookup_table_clear(table);
+        self.candidates.clear();
+        for candidate in candidates {
+            match into_ibus_string(candidate.display.clone()) {
+                Ok(ibus_text) => {
+                    ibus_lookup_table_append_candidate(table, ibus_text);
+                    self.candidates.push(candidate);
+                }
+                Err(err) => {
+                    log::error!("Failed string conversion for word lookup: {}", err);
+                }
+            }
+        }
+        ibus_engine_update_lookup_table(self.parent_engine_as_ibus_engine(), table, GBOOL_TRUE);
+        self.update_preedit();
+    }
+
     unsafe fn word_commit(&mut self, input_idx: Option<guint>) {
         if !self.table_visible || self.input_mode != WordTable {
             log::error!("Word commit called outside word input mode");
@@ -264,36 +489,561 @@ impl EngineCore {
             ibus_lookup_table_get_cursor_pos(self.get_table())
         });
         log::info!("Word commit for idx {}", idx);
-        let candidate = ibus_lookup_table_get_candidate(self.get_table(), idx);
-        self.get_word_remainder(candidate)
-            .map(|remainder| {
-                self.commit_text(remainder)
-            });
+        match self.candidates.get(idx as usize).map(|candidate| candidate.commit.clone()) {
+            //a completion that replaces what was typed needs it to still be before the cursor
+            Some(word) if word_edit(self.word_buffer.as_str(), word.as_str()).0 > 0 && !self.is_before_cursor(self.word_buffer.as_str()) => {
+                log::info!("Not committing {}", word);
+                self.last_word = None;
+            }
+            Some(word) => {
+                let (typed_len, text) = word_edit(self.word_buffer.as_str(), word.as_str());
+                if typed_len > 0 {
+                    ibus_engine_delete_surrounding_text(self.parent_engine_as_ibus_engine(), -(typed_len as gint), typed_len as guint);
+                }
+                match into_ibus_string(text) {
+                    Ok(ibus_text) => self.commit_text(ibus_text),
+                    Err(err) => log::error!("Failed string conversion for word commit: {}", err)
+                }
+                let word = Predictor::fold_case(word.as_str());
+                if !self.is_private_field() {
+                    user_dictionary().record(word.as_str());
+                }
+                self.last_word = Some(word);
+            }
+            None => {
+                log::error!("No word candidate at idx {}", idx);
+            }
+        }
 
         self.word_buffer.clear();
         self.word_table_disable();
     }
 
-    unsafe fn get_word_remainder(&self, candidate: *mut IBusText) -> Option<*mut IBusText> {
-        match CStr::from_ptr((*candidate).text as *const c_char).to_str() {
-            Ok(word) => {
-                match into_ibus_string(String::from(&word[self.word_buffer.len()..])) {
-                    Ok(ibus_word) => {
-                        Some(ibus_word)
+    //returns the word as typed if it is unknown and, since spelling is being checked, not learned yet
+    unsafe fn learn_word(&mut self, check_spelling: bool) -> Option<String> {
+        if self.word_buffer.is_empty() {
+            //e.g. a space straight after committing a completion, which already set the last word
+            return None;
+        }
+        else if self.is_private_field() {
+            self.last_word = None;
+            return None;
+        }
+
+        //only plain words are worth learning; anything else is probably a URL, number or similar
+        let typed_word = trim_word(self.word_buffer.as_str());
+        let is_word = !typed_word.is_empty() && typed_word.chars().all(|c| c.is_alphabetic() || c == '\'' || c == '-');
+        if !is_word {
+            self.last_word = None;
+            return None;
+        }
+
+        let word = Predictor::fold_case(typed_word);
+        let mut unknown_word = None;
+        if !predictor().contains(word.as_str()) {
+            if user_dictionary().count(word.as_str()) > 0 {
+                user_dictionary().record(word.as_str());
+            } else if check_spelling {
+                //with any trailing punctuation, since that is on screen too
+                unknown_word = Some(self.word_buffer.clone());
+            } else {
+                EngineCore::learn_unknown_word(word.as_str());
+            }
+        }
+        self.last_word = Some(word);
+        unknown_word
+    }
+
+    fn learn_unknown_word(word: &str) {
+        if config().learn_unknown_words {
+            let word = Predictor::fold_case(trim_word(word));
+            log::info!("Learning new word {}", word);
+            user_dictionary().record(word.as_str());
+        }
+    }
+
+    //punctuation like "." or "," ends the current word, and what follows it isn't predicted from that word
+    unsafe fn end_word(&mut self, c: char) {
+        if self.input_mode == WordTable {
+            self.word_table_disable();
+        }
+        self.learn_word(false);
+        self.word_buffer.clear();
+        self.last_word = None;
+        self.commit_char(c);
+    }
+
+    /*
+    ** Spell checking methods **
+     */
+
+    //an unknown word was just finished with a space, so correct it if sure of the correction, and
+    //otherwise offer corrections if enabled. The word is as typed, including any trailing ' or -
+    unsafe fn check_word(&mut self, word: String) {
+        if !self.can_replace() {
+            EngineCore::learn_unknown_word(word.as_str());
+            return;
+        }
+
+        if config().autocorrect {
+            match predictor().autocorrection(trim_word(word.as_str()), &AutocorrectConfidence::default()) {
+                Ok(Some(correction)) => {
+                    let correction = with_trailing_punctuation(correction.commit.as_str(), word.as_str());
+                    self.autocorrect(word, correction);
+                    return;
+                }
+                Ok(None) => {}
+                Err(err) => {
+                    log::error!("{}", err);
+                }
+            }
+        }
+
+        if config().spell_check {
+            self.correction_table_enable(word);
+        } else {
+            EngineCore::learn_unknown_word(word.as_str());
+        }
+    }
+
+    //replaces the word and the space after it, e.g. "teh " with "the ", if they are still before the cursor
+    unsafe fn replace_before_cursor(&mut self, typed: &str, replacement: &str) -> bool {
+        if !self.is_before_cursor(format!("{} ", typed).as_str()) {
+            return false;
+        }
+
+        let (typed_len, text) = replacement_edit(typed, replacement);
+        ibus_engine_delete_surrounding_text(self.parent_engine_as_ibus_engine(), -(typed_len as gint), typed_len as guint);
+        match into_ibus_string(text) {
+            Ok(ibus_replacement) => self.commit_text(ibus_replacement),
+            Err(err) => log::error!("Failed string conversion for replacement: {}", err)
+        }
+        true
+    }
+
+    unsafe fn autocorrect(&mut self, word: String, correction: String) {
+        log::info!("Autocorrecting {} to {}", word, correction);
+        if !self.replace_before_cursor(word.as_str(), correction.as_str()) {
+            self.forget_word();
+            return;
+        }
+        self.last_word = Some(Predictor::fold_case(trim_word(correction.as_str())));
+        self.last_autocorrection = Some((word, correction));
+    }
+
+    //backspace straight after an autocorrection puts back the word as typed, which was meant after all
+    unsafe fn autocorrect_undo(&mut self, word: String, correction: String) -> gboolean {
+        log::info!("Undoing autocorrection of {} to {}", word, correction);
+        if !self.replace_before_cursor(correction.as_str(), word.as_str()) {
+            //an ordinary backspace then
+            self.forget_word();
+            return GBOOL_FALSE;
+        }
+        EngineCore::learn_unknown_word(word.as_str());
+        self.last_word = Some(Predictor::fold_case(trim_word(word.as_str())));
+        GBOOL_TRUE
+    }
+
+    //offers corrections for an unknown word that was just finished with a space
+    unsafe fn correction_table_enable(&mut self, word: String) {
+        let corrections = match predictor().corrections(trim_word(word.as_str())) {
+            Ok(corrections) => corrections,
+            Err(err) => {
+                log::error!("{}", err);
+                Vec::new()
+            }
+        };
+        if corrections.is_empty() {
+            EngineCore::learn_unknown_word(word.as_str());
+            return;
+        }
+
+        log::info!("Corrections for {} are {:?}", word, corrections);
+        self.input_mode = CorrectionTable;
+        self.table_visible = true;
+        self.misspelled_word = word;
+        let table = self.get_table();
+        ibus_lookup_table_clear(table);
+        self.candidates.clear();
+        for candidate in corrections {
+            match into_ibus_string(candidate.display.clone()) {
+                Ok(ibus_text) => {
+                    ibus_lookup_table_append_candidate(table, ibus_text);
+                    self.candidates.push(candidate);
+                }
+                Err(err) => {
+                    log::error!("Failed string conversion for correction lookup: {}", err);
+                }
+            }
+        }
+        ibus_engine_update_lookup_table(self.parent_engine_as_ibus_engine(), table, GBOOL_TRUE);
+    }
+
+    unsafe fn correction_table_disable(&mut self) -> gboolean {
+        if self.input_mode != CorrectionTable {
+            log::error!("Call to disable correction table outside of correction mode");
+            return GBOOL_FALSE;
+        }
+
+        self.input_mode = Normal;
+        self.table_visible = false;
+        self.misspelled_word.clear();
+        ibus_engine_hide_lookup_table(self.parent_engine_as_ibus_engine());
+        GBOOL_TRUE
+    }
+
+    //the word was meant as typed
+    unsafe fn correction_dismiss(&mut self) -> gboolean {
+        EngineCore::learn_unknown_word(self.misspelled_word.as_str());
+        self.correction_table_disable()
+    }
+
+    unsafe fn correction_commit(&mut self, input_idx: Option<guint>) {
+        if self.input_mode != CorrectionTable {
+            log::error!("Correction commit called outside correction mode");
+            return;
+        }
+
+        let idx = input_idx.unwrap_or_else(|| {
+            ibus_lookup_table_get_cursor_pos(self.get_table())
+        });
+        match self.candidates.get(idx as usize).map(|candidate| candidate.commit.clone()) {
+            Some(correction) => {
+                let misspelled_word = self.misspelled_word.clone();
+                let replacement = with_trailing_punctuation(correction.as_str(), misspelled_word.as_str());
+                if self.replace_before_cursor(misspelled_word.as_str(), replacement.as_str()) {
+                    let correction = Predictor::fold_case(correction.as_str());
+                    if !self.is_private_field() {
+                        user_dictionary().record(correction.as_str());
                     }
-                    Err(err) => {
-                        log::error!("Failed to convert slice back into ibus string: {}", err);
-                        None
+                    self.last_word = Some(correction);
+                } else {
+                    self.forget_word();
+                }
+            }
+            None => {
+                log::error!("No correction candidate at idx {}", idx);
+            }
+        }
+        self.correction_table_disable();
+    }
+
+    /*
+    ** Code point input methods **
+     */
+
+    unsafe fn codepoint_enable(&mut self) -> gboolean {
+        if self.table_visible || self.input_mode != Normal {
+            log::error!("Call to enable code point entry while another input mode is active");
+            return GBOOL_FALSE;
+        }
+
+        self.input_mode = CodepointEntry;
+        self.codepoint_preedit.clear();
+        self.codepoint_update();
+        GBOOL_TRUE
+    }
+
+    unsafe fn codepoint_disable(&mut self) -> gboolean {
+        if self.input_mode != CodepointEntry {
+            log::error!("Call to disable code point entry outside of code point entry mode");
+            return GBOOL_FALSE;
+        }
+
+        self.input_mode = Normal;
+        self.codepoint_preedit.clear();
+        ibus_engine_hide_preedit_text(self.parent_engine_as_ibus_engine());
+        ibus_engine_hide_auxiliary_text(self.parent_engine_as_ibus_engine());
+        GBOOL_TRUE
+    }
+
+    //the aux text shows the code point with its character and name, or why it can't be typed,
+    //and the preedit shows the character that will be committed
+    unsafe fn codepoint_update(&mut self) {
+        let predictor = predictor();
+        let preview = codepoint::preview(self.codepoint_preedit.as_str(), |c| predictor.character_name(c));
+        match into_ibus_string(preview) {
+            Ok(ibus_string) => {
+                ibus_engine_update_auxiliary_text(self.parent_engine_as_ibus_engine(), ibus_string, GBOOL_TRUE);
+            }
+            Err(err) => {
+                log::error!("Failed string conversion for code point aux text update: {}", err);
+            }
+        }
+
+        let preedit = match parse_codepoint(self.codepoint_preedit.as_str()) {
+            Ok(c) => c.to_string(),
+            Err(_err) => String::new()
+        };
+        match into_ibus_string(preedit) {
+            Ok(text) => {
+                let len = ibus_text_get_length(text);
+                ibus_text_append_attribute(text, IBusAttrType_IBUS_ATTR_TYPE_UNDERLINE,
+                                           IBusAttrUnderline_IBUS_ATTR_UNDERLINE_SINGLE, 0, len as gint);
+                ibus_engine_update_preedit_text(self.parent_engine_as_ibus_engine(), text, len, GBOOL_TRUE);
+            }
+            Err(err) => {
+                log::error!("Failed string conversion for code point preedit: {}", err);
+            }
+        }
+    }
+
+    unsafe fn codepoint_key(&mut self, keyval: guint) -> gboolean {
+        match keyval {
+            IBUS_Return | IBUS_space => {
+                self.codepoint_commit()
+            }
+            IBUS_Escape => {
+                self.codepoint_disable()
+            }
+            IBUS_BackSpace => {
+                if self.codepoint_preedit.pop().is_none() {
+                    self.codepoint_disable();
+                } else {
+                    self.codepoint_update();
+                }
+                GBOOL_TRUE
+            }
+            _ => {
+                match keyval_char(keyval) {
+                    Some(c) if c.is_ascii_hexdigit() && self.codepoint_preedit.len() < MAX_DIGITS => {
+                        self.codepoint_preedit.push(c);
+                        self.codepoint_update();
                     }
+                    //anything else would be typed into the application in the middle of the code point
+                    _ => {}
                 }
+                GBOOL_TRUE
+            }
+        }
+    }
+
+    //an invalid code point stays open so it can be fixed
+    unsafe fn codepoint_commit(&mut self) -> gboolean {
+        match parse_codepoint(self.codepoint_preedit.as_str()) {
+            Ok(c) => {
+                self.codepoint_disable();
+                self.commit_char(c);
+                self.word_buffer.clear();
             }
             Err(err) => {
-                log::error!("Failed to convert word to string: {}", err);
-                None
+                log::info!("Not committing code point {}: {}", self.codepoint_preedit, err);
+            }
+        }
+        GBOOL_TRUE
+    }
+
+    /*
+    ** Superscript and subscript input methods **
+     */
+
+    unsafe fn script_enable(&mut self, script: Script) -> gboolean {
+        if self.table_visible || self.input_mode != Normal {
+            log::error!("Call to enable {} input while another input mode is active", script);
+            return GBOOL_FALSE;
+        }
+
+        self.input_mode = ScriptEntry(script);
+        self.script_preedit.clear();
+        self.script_update(script, None);
+        GBOOL_TRUE
+    }
+
+    unsafe fn script_disable(&mut self) -> gboolean {
+        if !matches!(self.input_mode, ScriptEntry(_)) {
+            log::error!("Call to disable superscript/subscript input outside of that input mode");
+            return GBOOL_FALSE;
+        }
+
+        self.input_mode = Normal;
+        self.script_preedit.clear();
+        ibus_engine_hide_preedit_text(self.parent_engine_as_ibus_engine());
+        ibus_engine_hide_auxiliary_text(self.parent_engine_as_ibus_engine());
+        GBOOL_TRUE
+    }
+
+    //the aux text names the mode, and says so when the last character typed has no converted form
+    unsafe fn script_update(&mut self, script: Script, rejected: Option<char>) {
+        match into_ibus_string(script.preview(rejected)) {
+            Ok(ibus_string) => {
+                ibus_engine_update_auxiliary_text(self.parent_engine_as_ibus_engine(), ibus_string, GBOOL_TRUE);
+            }
+            Err(err) => {
+                log::error!("Failed string conversion for {} aux text update: {}", script, err);
+            }
+        }
+
+        match into_ibus_string(self.script_preedit.clone()) {
+            Ok(text) => {
+                let len = ibus_text_get_length(text);
+                ibus_text_append_attribute(text, IBusAttrType_IBUS_ATTR_TYPE_UNDERLINE,
+                                           IBusAttrUnderline_IBUS_ATTR_UNDERLINE_SINGLE, 0, len as gint);
+                ibus_engine_update_preedit_text(self.parent_engine_as_ibus_engine(), text, len, GBOOL_TRUE);
+            }
+            Err(err) => {
+                log::error!("Failed string conversion for {} preedit: {}", script, err);
+            }
+        }
+    }
+
+    unsafe fn script_key(&mut self, script: Script, keyval: guint) -> gboolean {
+        match keyval {
+            IBUS_Return | IBUS_space => {
+                self.script_commit()
+            }
+            IBUS_Escape => {
+                self.script_disable()
+            }
+            IBUS_BackSpace => {
+                if self.script_preedit.pop().is_none() {
+                    self.script_disable();
+                } else {
+                    self.script_update(script, None);
+                }
+                GBOOL_TRUE
+            }
+            _ => {
+                let c = match keyval_char(keyval) {
+                    Some(c) => c,
+                    None => return GBOOL_FALSE
+                };
+                match script.convert(c) {
+                    Some(converted) => {
+                        self.script_preedit.push(converted);
+                        self.script_update(script, None);
+                    }
+                    None => {
+                        self.script_update(script, Some(c));
+                    }
+                }
+                GBOOL_TRUE
             }
         }
     }
 
+    unsafe fn script_commit(&mut self) -> gboolean {
+        let text = self.script_preedit.clone();
+        self.script_disable();
+        if !text.is_empty() {
+            match into_ibus_string(text) {
+                Ok(text) => self.commit_text(text),
+                Err(err) => log::error!("Failed string conversion for superscript/subscript commit: {}", err)
+            }
+            self.word_buffer.clear();
+        }
+        GBOOL_TRUE
+    }
+
+    /*
+    ** Accent composition methods **
+     */
+
+    unsafe fn compose_enable(&mut self) -> gboolean {
+        if self.table_visible || self.input_mode != Normal {
+            log::error!("Call to enable accent composition while another input mode is active");
+            return GBOOL_FALSE;
+        }
+
+        self.input_mode = ComposeEntry;
+        self.compose_letter = None;
+        self.compose_update(None);
+        GBOOL_TRUE
+    }
+
+    unsafe fn compose_disable(&mut self) -> gboolean {
+        if self.input_mode != ComposeEntry {
+            log::error!("Call to disable accent composition outside of composition mode");
+            return GBOOL_FALSE;
+        }
+
+        self.input_mode = Normal;
+        self.compose_letter = None;
+        ibus_engine_hide_preedit_text(self.parent_engine_as_ibus_engine());
+        ibus_engine_hide_auxiliary_text(self.parent_engine_as_ibus_engine());
+        GBOOL_TRUE
+    }
+
+    //the preedit shows the letter waiting for its accent, and the aux text says when an accent doesn't apply
+    unsafe fn compose_update(&mut self, rejected: Option<char>) {
+        match into_ibus_string(compose::preview(self.compose_letter, rejected)) {
+            Ok(ibus_string) => {
+                ibus_engine_update_auxiliary_text(self.parent_engine_as_ibus_engine(), ibus_string, GBOOL_TRUE);
+            }
+            Err(err) => {
+                log::error!("Failed string conversion for compose aux text update: {}", err);
+            }
+        }
+
+        match into_ibus_string(self.compose_letter.map(String::from).unwrap_or_default()) {
+            Ok(text) => {
+                let len = ibus_text_get_length(text);
+                ibus_text_append_attribute(text, IBusAttrType_IBUS_ATTR_TYPE_UNDERLINE,
+                                           IBusAttrUnderline_IBUS_ATTR_UNDERLINE_SINGLE, 0, len as gint);
+                ibus_engine_update_preedit_text(self.parent_engine_as_ibus_engine(), text, len, GBOOL_TRUE);
+            }
+            Err(err) => {
+                log::error!("Failed string conversion for compose preedit: {}", err);
+            }
+        }
+    }
+
+    unsafe fn compose_key(&mut self, keyval: guint) -> gboolean {
+        match keyval {
+            //the letter is kept as typed, without an accent
+            IBUS_Return => {
+                let letter = self.compose_letter;
+                self.compose_disable();
+                if let Some(letter) = letter {
+                    self.word_buffer.push(letter);
+                    self.commit_char(letter);
+                }
+                GBOOL_TRUE
+            }
+            IBUS_Escape | IBUS_Tab => {
+                self.compose_disable()
+            }
+            IBUS_BackSpace => {
+                if self.compose_letter.take().is_none() {
+                    self.compose_disable();
+                } else {
+                    self.compose_update(None);
+                }
+                GBOOL_TRUE
+            }
+            _ => {
+                let c = match keyval_char(keyval) {
+                    Some(c) => c,
+                    //a key that may move the cursor gives up on the letter, so it isn't composed somewhere else
+                    None if forgets_word(keyval) => {
+                        self.compose_disable();
+                        self.forget_word();
+                        return GBOOL_FALSE;
+                    }
+                    None => return GBOOL_FALSE
+                };
+                match self.compose_letter {
+                    None if c.is_alphabetic() => {
+                        self.compose_letter = Some(c);
+                        self.compose_update(None);
+                    }
+                    //only letters take accents
+                    None => {}
+                    Some(letter) => match compose::compose(letter, c) {
+                        Some(accented) => {
+                            //typed as if it came from the keyboard, so it becomes part of the word being completed
+                            self.compose_disable();
+                            self.word_buffer.push(accented);
+                            self.commit_char(accented);
+                        }
+                        None => {
+                            self.compose_update(Some(c));
+                        }
+                    }
+                }
+                GBOOL_TRUE
+            }
+        }
+    }
 
     /*
     ** Symbol input methods **
@@ -307,9 +1057,10 @@ impl EngineCore {
 
         self.input_mode = SymbolTable;
         self.table_visible = true;
-        self.symbol_last_page = 0;
-        ibus_lookup_table_clear(self.get_table());
-        ibus_engine_update_lookup_table(self.parent_engine_as_ibus_engine(), self.get_table(), GBOOL_TRUE);
+        //before anything is typed, offer the symbols used recently and most often
+        let suggestions = predictor().symbol_suggestions(&symbol_history(), 2 * (*self.get_table()).page_size as usize);
+        self.symbol_table_fill(suggestions);
+        self.update_preedit();
         GBOOL_TRUE
     }
 
@@ -322,6 +1073,7 @@ impl EngineCore {
         self.input_mode = Normal;
         self.table_visible = false;
         self.symbol_preedit.clear();
+        self.symbol_variants_visible = false;
         ibus_engine_hide_preedit_text(self.parent_engine_as_ibus_engine());
         ibus_engine_hide_lookup_table(self.parent_engine_as_ibus_engine());
         ibus_engine_hide_auxiliary_text(self.parent_engine_as_ibus_engine());
@@ -346,35 +1098,15 @@ impl EngineCore {
             }
         }
 
+        self.symbol_variants_visible = false;
         if self.symbol_preedit.is_empty() {
             return;
         }
 
-        let search_result  = PREDICTOR.symbol(self.symbol_preedit.as_str());
-        match search_result {
+        match symbol_search(self.symbol_preedit.as_str()) {
             Ok(candidates) => {
                 log::info!("Symbol search for {} and got {:?}", self.symbol_preedit, candidates);
-                let table = self.get_table();
-                // Must clear table first, since the table may have IBusText referencing the
-                // symbol_label_vec strings
-                ibus_lookup_table_clear(table);
-                self.symbol_label_vec.clear();
-                for (idx, (shortcode, ident)) in candidates.into_iter().enumerate() {
-                    match (CString::new(shortcode.into_bytes()),  CString::new(ident.into_bytes())) {
-                        (Ok(shortcode_cstring), Ok(ident_cstring)) => {
-                            ibus_lookup_table_append_candidate(table, ibus_text_new_from_string(shortcode_cstring.into_raw() as *mut gchar));
-                            self.symbol_label_vec.push(ident_cstring);
-                            if idx < (*table).page_size as usize {
-                                ibus_lookup_table_set_label(table, idx as guint, ibus_text_new_from_static_string(self.symbol_label_vec.get_unchecked(idx).as_ptr()));
-                            }
-                        }
-                        _ => {
-                            log::error!("Failed string conversion for symbol lookup");
-                        }
-                    }
-                }
-                log::info!("{} candidates and {} labels", ibus_lookup_table_get_number_of_candidates(self.get_table()), self.symbol_label_vec.len());
-                ibus_engine_update_lookup_table(self.parent_engine_as_ibus_engine(), table, GBOOL_TRUE);
+                self.symbol_table_fill(candidates);
             },
             Err(err) => {
                 log::error!("{}", err);
@@ -383,17 +1115,97 @@ impl EngineCore {
         self.update_preedit();
     }
 
+    unsafe fn symbol_table_fill(&mut self, candidates: Vec<Candidate>) {
+        let table = self.get_table();
+        // Must clear table first, since the table may have IBusText referencing the
+        // symbol_label_vec strings
+        ibus_lookup_table_clear(table);
+        self.symbol_label_vec.clear();
+        self.candidates.clear();
+        self.symbol_last_page = 0;
+        for candidate in candidates {
+            //snippets can be long, so only the start is shown
+            let label = match candidate.source {
+                CandidateSource::Snippet => Snippets::preview(candidate.commit.as_str()),
+                _ => candidate.commit.clone()
+            };
+            match (CString::new(candidate.display.as_bytes()),  CString::new(label.into_bytes())) {
+                (Ok(shortcode_cstring), Ok(ident_cstring)) => {
+                    let idx = self.candidates.len();
+                    ibus_lookup_table_append_candidate(table, ibus_text_new_from_string(shortcode_cstring.into_raw() as *mut gchar));
+                    self.symbol_label_vec.push(ident_cstring);
+                    self.candidates.push(candidate);
+                    if idx < (*table).page_size as usize {
+                        ibus_lookup_table_set_label(table, idx as guint, ibus_text_new_from_static_string(self.symbol_label_vec.get_unchecked(idx).as_ptr()));
+                    }
+                }
+                _ => {
+                    log::error!("Failed string conversion for symbol lookup");
+                }
+            }
+        }
+        log::info!("{} candidates and {} labels", ibus_lookup_table_get_number_of_candidates(self.get_table()), self.symbol_label_vec.len());
+        ibus_engine_update_lookup_table(self.parent_engine_as_ibus_engine(), table, GBOOL_TRUE);
+    }
+
+    //swaps the table between the search results and the skin tone/gender variants of the highlighted symbol
+    unsafe fn symbol_variants_toggle(&mut self) -> gboolean {
+        if self.symbol_variants_visible {
+            self.symbol_input_update();
+            return GBOOL_TRUE;
+        }
+
+        let idx = ibus_lookup_table_get_cursor_pos(self.get_table());
+        if idx >= ibus_lookup_table_get_number_of_candidates(self.get_table()) {
+            return GBOOL_FALSE;
+        }
+        let variants = match self.candidates.get(idx as usize) {
+            Some(candidate) => predictor().symbol_variants(candidate.display.as_str(), candidate.commit.as_str()),
+            None => {
+                log::error!("No symbol candidate at idx {}", idx);
+                return GBOOL_FALSE;
+            }
+        };
+        if variants.len() <= 1 {
+            return GBOOL_TRUE;
+        }
+        log::info!("Variants are {:?}", variants);
+        self.symbol_table_fill(variants);
+        self.symbol_variants_visible = true;
+        self.update_preedit();
+        GBOOL_TRUE
+    }
+
+    fn symbol_record(candidate: &Candidate) {
+        //snippets are found by their abbreviation, and may expand differently next time
+        if candidate.source != CandidateSource::Snippet {
+            symbol_history().record(candidate.display.as_str(), candidate.commit.as_str());
+        }
+    }
+
     unsafe fn symbol_commit(&mut self, input_idx: Option<guint>) {
         if self.input_mode != SymbolTable {
             log::error!("Symbol input commit called outside symbol input mode");
         }
 
-        if !self.symbol_preedit.is_empty() {
-            let idx = input_idx.unwrap_or_else(|| {
+        if ibus_lookup_table_get_number_of_candidates(self.get_table()) > 0 {
+            let idx_in_page = input_idx.unwrap_or_else(|| {
                 ibus_lookup_table_get_cursor_in_page(self.get_table())
             });
-            let symbol = ibus_lookup_table_get_label(self.get_table(), idx);
-            self.commit_text(symbol);
+            let page_size = (*self.get_table()).page_size;
+            let idx = ibus_lookup_table_get_cursor_pos(self.get_table()) / page_size * page_size + idx_in_page;
+            match self.candidates.get(idx as usize).cloned() {
+                Some(candidate) => {
+                    EngineCore::symbol_record(&candidate);
+                    match into_ibus_string(candidate.commit) {
+                        Ok(symbol) => self.commit_text(symbol),
+                        Err(err) => log::error!("Failed string conversion for symbol commit: {}", err)
+                    }
+                }
+                None => {
+                    log::error!("No symbol candidate at idx {}", idx);
+                }
+            }
         }
 
         self.symbol_table_disable();
@@ -403,20 +1215,127 @@ impl EngineCore {
 
 #[no_mangle]
 pub unsafe extern "C" fn free_engine_core(engine_state: *mut EngineCore) {
+    user_dictionary().flush();
+    symbol_history().flush();
     std::mem::drop(Box::from_raw(engine_state));
 }
 
+#[repr(C)]
+pub struct PredictionCandidate {
+    display: *mut c_char,
+    commit: *mut c_char,
+    score: u64,
+    source: CandidateSource,
+    //null if the candidate has no description
+    description: *mut c_char
+}
+
+impl PredictionCandidate {
+    fn new(candidate: Candidate) -> Result<PredictionCandidate, NulError> {
+        let description = match candidate.description {
+            Some(description) => CString::new(description)?.into_raw(),
+            None => std::ptr::null_mut()
+        };
+        Ok(PredictionCandidate {
+            display: CString::new(candidate.display)?.into_raw(),
+            commit: CString::new(candidate.commit)?.into_raw(),
+            score: candidate.score,
+            source: candidate.source,
+            description
+        })
+    }
+
+    unsafe fn free(&self) {
+        for string in [self.display, self.commit, self.description].iter() {
+            if !string.is_null() {
+                std::mem::drop(CString::from_raw(*string));
+            }
+        }
+    }
+}
+
+//candidates that can't be passed to C (i.e. contain a nul) are left out
+fn into_prediction_candidates(candidates: Vec<Candidate>) -> (c_int, *mut PredictionCandidate) {
+    let candidates = candidates.into_iter()
+        .filter_map(|candidate| match PredictionCandidate::new(candidate) {
+            Ok(candidate) => Some(candidate),
+            Err(err) => {
+                log::error!("Failed string conversion for prediction candidate: {}", err);
+                None
+            }
+        })
+        .collect::<Vec<_>>()
+        .into_boxed_slice();
+    (candidates.len() as c_int, Box::into_raw(candidates) as *mut PredictionCandidate)
+}
+
+unsafe fn free_prediction_candidates(len: c_int, candidates: *mut PredictionCandidate) {
+    let candidates = Box::from_raw(std::ptr::slice_from_raw_parts_mut(candidates, len as usize));
+    for candidate in candidates.iter() {
+        candidate.free();
+    }
+}
+
+unsafe fn c_str_arg(arg: *const c_char) -> Option<String> {
+    if arg.is_null() {
+        None
+    } else {
+        Some(CStr::from_ptr(arg).to_string_lossy().into_owned())
+    }
+}
+
 #[repr(C)]
 pub struct WordPredictions {
     len: c_int,
-    words: *mut *mut c_char
+    candidates: *mut PredictionCandidate
 }
 
 #[repr(C)]
 pub struct SymbolPredictions {
     len: c_int,
-    symbols: *mut *mut c_char,
-    shortcodes: *mut *mut c_char
+    candidates: *mut PredictionCandidate
+}
+
+//what the word table would show for the prefix, after the previous word if it isn't null;
+//the result must be freed with free_word_predictions
+#[no_mangle]
+pub unsafe extern "C" fn predict_words(previous: *const c_char, prefix: *const c_char) -> *mut WordPredictions {
+    let previous = c_str_arg(previous);
+    let candidates = match word_search(previous.as_deref(), c_str_arg(prefix).unwrap_or_default().as_str()) {
+        Ok(candidates) => candidates,
+        Err(err) => {
+            log::error!("{}", err);
+            Vec::new()
+        }
+    };
+    let (len, candidates) = into_prediction_candidates(candidates);
+    Box::into_raw(Box::new(WordPredictions { len, candidates }))
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn free_word_predictions(predictions: *mut WordPredictions) {
+    let predictions = Box::from_raw(predictions);
+    free_prediction_candidates(predictions.len, predictions.candidates);
+}
+
+//what the symbol table would show for the query; the result must be freed with free_symbol_predictions
+#[no_mangle]
+pub unsafe extern "C" fn predict_symbols(query: *const c_char) -> *mut SymbolPredictions {
+    let candidates = match symbol_search(c_str_arg(query).unwrap_or_default().as_str()) {
+        Ok(candidates) => candidates,
+        Err(err) => {
+            log::error!("{}", err);
+            Vec::new()
+        }
+    };
+    let (len, candidates) = into_prediction_candidates(candidates);
+    Box::into_raw(Box::new(SymbolPredictions { len, candidates }))
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn free_symbol_predictions(predictions: *mut SymbolPredictions) {
+    let predictions = Box::from_raw(predictions);
+    free_prediction_candidates(predictions.len, predictions.candidates);
 }
 
 #[no_mangle]
@@ -447,6 +1366,8 @@ pub unsafe extern "C" fn ibus_eei_engine_focus_out(engine: *mut IBusEngine) {
     match EngineCore::get(engine) {
         Some(engine_core) => {
             engine_core.abort_table_input();
+            user_dictionary().flush();
+            symbol_history().flush();
             match (*engine_core.parent_engine_class).focus_out {
                 Some(parent_focus_out) => {
                     parent_focus_out(engine);
@@ -467,6 +1388,8 @@ pub unsafe extern "C" fn ibus_eei_engine_reset(engine: *mut IBusEngine) {
     match EngineCore::get(engine) {
         Some(engine_core) => {
             engine_core.abort_table_input();
+            user_dictionary().flush();
+            symbol_history().flush();
             match (*engine_core.parent_engine_class).reset {
                 Some(parent_reset) => {
                     parent_reset(engine);
@@ -488,7 +1411,7 @@ pub unsafe extern "C" fn ibus_eei_engine_candidate_clicked(engine: *mut IBusEngi
 
     match EngineCore::get(engine) {
         Some(engine_core) => {
-            let offset = if engine_core.input_mode == WordTable {
+            let offset = if engine_core.input_mode == WordTable || engine_core.input_mode == CorrectionTable {
                 //the event input indx is relative, not absolute, so we have to compute where we are
                 //in the entire table and not just this page
                 let page_size = (*engine_core.get_table()).page_size;
@@ -519,6 +1442,27 @@ pub unsafe extern "C" fn ibus_eei_engine_process_key_event(engine: *mut IBusEngi
         }
     };
 
+    if (modifiers & IBusModifierType_IBUS_RELEASE_MASK) == 0 {
+        if let Some((word, correction)) = engine_core.last_autocorrection.take() {
+            if keyval == IBUS_BackSpace && modifiers == 0 {
+                return engine_core.autocorrect_undo(word, correction);
+            }
+        }
+    }
+
+    //the correction table only handles keys for picking from it, anything else means the word was meant as typed
+    if engine_core.input_mode == CorrectionTable && (modifiers & IBusModifierType_IBUS_RELEASE_MASK) == 0 {
+        match keyval {
+            IBUS_Up | IBUS_Down | IBUS_Page_Up | IBUS_Page_Down | IBUS_Return | IBUS_Escape => {}
+            IBUS_BackSpace => {
+                //fixing the word by hand, so it shouldn't be learned
+                engine_core.correction_table_disable();
+            }
+            _ => {
+                engine_core.correction_dismiss();
+            }
+        }
+    }
 
     if modifiers == IBusModifierType_IBUS_CONTROL_MASK {
         //control key (and only control key) is held down
@@ -528,7 +1472,7 @@ pub unsafe extern "C" fn ibus_eei_engine_process_key_event(engine: *mut IBusEngi
                     SymbolTable => {
                         engine_core.symbol_table_disable()
                     }
-                    WordTable => {GBOOL_FALSE}
+                    WordTable | CorrectionTable | CodepointEntry | ScriptEntry(_) | ComposeEntry => {GBOOL_FALSE}
                     Normal => {
                         engine_core.symbol_table_enable()
                     }
@@ -536,7 +1480,7 @@ pub unsafe extern "C" fn ibus_eei_engine_process_key_event(engine: *mut IBusEngi
             }
             IBUS_w => {
                 match engine_core.input_mode {
-                    SymbolTable => {GBOOL_FALSE}
+                    SymbolTable | CorrectionTable | CodepointEntry | ScriptEntry(_) | ComposeEntry => {GBOOL_FALSE}
                     WordTable => {
                         engine_core.word_table_disable()
                     }
@@ -545,6 +1489,17 @@ pub unsafe extern "C" fn ibus_eei_engine_process_key_event(engine: *mut IBusEngi
                     }
                 }
             }
+            _ if Some(keyval) == config().compose_hotkey.map(|hotkey| hotkey.to_ascii_lowercase() as guint) => {
+                match engine_core.input_mode {
+                    SymbolTable | WordTable | CorrectionTable | CodepointEntry | ScriptEntry(_) => {GBOOL_FALSE}
+                    ComposeEntry => {
+                        engine_core.compose_disable()
+                    }
+                    Normal => {
+                        engine_core.compose_enable()
+                    }
+                }
+            }
             _ => {
                 GBOOL_FALSE
             }
@@ -553,6 +1508,13 @@ pub unsafe extern "C" fn ibus_eei_engine_process_key_event(engine: *mut IBusEngi
         return GBOOL_FALSE; //This also covers released keys with IBUS_RELEASE_MASK
     }
 
+    match engine_core.input_mode {
+        CodepointEntry => return engine_core.codepoint_key(keyval),
+        ScriptEntry(script) => return engine_core.script_key(script, keyval),
+        ComposeEntry => return engine_core.compose_key(keyval),
+        SymbolTable | WordTable | CorrectionTable | Normal => {}
+    }
+
     match keyval {
         IBUS_space => {
             match engine_core.input_mode {
@@ -562,20 +1524,30 @@ pub unsafe extern "C" fn ibus_eei_engine_process_key_event(engine: *mut IBusEngi
                 WordTable => {
                     engine_core.word_table_disable();
                 }
-                Normal => {}
+                CorrectionTable | CodepointEntry | ScriptEntry(_) | ComposeEntry | Normal => {}
             }
-            engine_core.commit_char(keyval);
+            let unknown_word = engine_core.learn_word(config().spell_check || config().autocorrect);
+            engine_core.commit_char(' ');
             engine_core.word_buffer.clear();
+            if let Some(word) = unknown_word {
+                engine_core.check_word(word);
+            }
             GBOOL_TRUE
         }
+        IBUS_Tab if engine_core.input_mode == SymbolTable => {
+            engine_core.symbol_variants_toggle()
+        }
         IBUS_Return => {
+            if engine_core.input_mode == Normal {
+                engine_core.last_word = None;
+            }
             engine_core.commit_from_table(None)
         }
         IBUS_Right | IBUS_Left => {
             if engine_core.input_mode == WordTable {
                 engine_core.word_table_disable();
             }
-            engine_core.word_buffer.clear();
+            engine_core.forget_word();
             GBOOL_FALSE
         }
         IBUS_Up => {
@@ -584,6 +1556,7 @@ pub unsafe extern "C" fn ibus_eei_engine_process_key_event(engine: *mut IBusEngi
                 engine_core.update_lookup_table();
                 ret
             } else {
+                engine_core.forget_word();
                 GBOOL_FALSE
             }
         }
@@ -593,6 +1566,7 @@ pub unsafe extern "C" fn ibus_eei_engine_process_key_event(engine: *mut IBusEngi
                 engine_core.update_lookup_table();
                 ret
             } else {
+                engine_core.forget_word();
                 GBOOL_FALSE
             }
         }
@@ -608,12 +1582,17 @@ pub unsafe extern "C" fn ibus_eei_engine_process_key_event(engine: *mut IBusEngi
                     GBOOL_TRUE
                 }
                 WordTable => {
-                    engine_core.word_buffer.pop();
+                    if engine_core.word_buffer.pop().is_none() {
+                        engine_core.last_word = None;
+                    }
                     engine_core.word_table_update();
                     GBOOL_FALSE //let the character pass through so deletion still happens
                 }
-                Normal => {
-                    engine_core.word_buffer.pop();
+                CorrectionTable | CodepointEntry | ScriptEntry(_) | ComposeEntry | Normal => {
+                    if engine_core.word_buffer.pop().is_none() {
+                        //deleting text before the current word, so the previous word is no longer known
+                        engine_core.last_word = None;
+                    }
                     GBOOL_FALSE
                 }
             }
@@ -627,35 +1606,75 @@ pub unsafe extern "C" fn ibus_eei_engine_process_key_event(engine: *mut IBusEngi
         IBUS_Escape => {
             engine_core.abort_table_input()
         }
-        IBUS_space..=IBUS_asciitilde => {
+        _ => {
+            let c = match keyval_char(keyval) {
+                Some(c) => c,
+                None => {
+                    if forgets_word(keyval) {
+                        match engine_core.input_mode {
+                            WordTable => {
+                                engine_core.word_table_disable();
+                                engine_core.forget_word();
+                            }
+                            Normal => {
+                                engine_core.forget_word();
+                            }
+                            SymbolTable | CorrectionTable | CodepointEntry | ScriptEntry(_) | ComposeEntry => {}
+                        }
+                    }
+                    return GBOOL_FALSE
+                }
+            };
             match engine_core.input_mode {
                 SymbolTable => {
-                    engine_core.symbol_preedit.push((keyval as u8) as char);
-                    engine_core.symbol_input_update();
+                    engine_core.symbol_preedit.push(c);
+                    //"u+", "^" and "_" switch to typing a code point, superscript or subscript
+                    if codepoint::is_prefix(engine_core.symbol_preedit.as_str()) {
+                        engine_core.symbol_table_disable();
+                        engine_core.codepoint_enable();
+                    } else if let Some(script) = Script::from_prefix(engine_core.symbol_preedit.as_str()) {
+                        engine_core.symbol_table_disable();
+                        engine_core.script_enable(script);
+                    } else {
+                        engine_core.symbol_input_update();
+                    }
                 }
-                WordTable => {
-                    engine_core.commit_char(keyval);
+                WordTable if is_word_char(c, engine_core.word_buffer.as_str()) => {
+                    engine_core.word_buffer.push(c);
+                    engine_core.commit_char(c);
                     engine_core.word_table_update();
                 }
-                Normal => {
-                    engine_core.commit_char(keyval);
+                CorrectionTable | CodepointEntry | ScriptEntry(_) | ComposeEntry | Normal if is_word_char(c, engine_core.word_buffer.as_str()) => {
+                    engine_core.word_buffer.push(c);
+                    engine_core.commit_char(c);
+                }
+                WordTable | CorrectionTable | CodepointEntry | ScriptEntry(_) | ComposeEntry | Normal => {
+                    engine_core.end_word(c);
                 }
             }
             GBOOL_TRUE
         }
-        _ => GBOOL_FALSE
     }
 }
 
 
 static DATA_DIRNAME: &str = "eei";
 
+fn user_data_dir() -> Result<PathBuf, std::env::VarError> {
+    std::env::var("XDG_DATA_HOME").map(|dir| Path::new(dir.as_str()).join(DATA_DIRNAME))
+        .or(std::env::var("HOME").map(|home| Path::new(home.as_str()).join(".local").join("share").join(DATA_DIRNAME)))
+}
+
+fn user_config_dir() -> Result<PathBuf, std::env::VarError> {
+    std::env::var("XDG_CONFIG_HOME").map(|dir| Path::new(dir.as_str()).join(DATA_DIRNAME))
+        .or(std::env::var("HOME").map(|home| Path::new(home.as_str()).join(".config").join(DATA_DIRNAME)))
+}
+
 #[no_mangle]
 pub unsafe extern "C" fn configure_logging() {
     //https://stackoverflow.com/questions/56345288/how-do-i-use-log4rs-rollingfileappender-to-incorporate-rolling-logging
 
-    let log_location = std::env::var("XDG_DATA_HOME").map(|dir| Path::new(dir.as_str()).join(DATA_DIRNAME))
-        .or(std::env::var("HOME").map(|home| Path::new(home.as_str()).join(".local").join("share").join(DATA_DIRNAME)));
+    let log_location = user_data_dir();
 
     match log_location {
         Ok(location) => {
@@ -702,3 +1721,110 @@ pub unsafe extern "C" fn configure_logging() {
 }
 
 
+#[cfg(test)]
+mod tests {
+    use crate::{forgets_word, free_prediction_candidates, free_symbol_predictions, free_word_predictions, into_prediction_candidates, is_word_char, keyval_char, predict_symbols, predict_words, replacement_edit, typed_before_cursor, with_trailing_punctuation, word_edit, PredictionCandidate};
+    use ibus::{guint, IBUS_a, IBUS_b, IBUS_Control_L, IBUS_Delete, IBUS_End, IBUS_f, IBUS_Home, IBUS_ISO_Level3_Shift, IBUS_o, IBUS_r, IBUS_Shift_L, IBUS_Tab, IBUS_Up};
+    use crate::candidate::{Candidate, CandidateSource};
+    use std::ffi::{CStr, CString};
+
+    #[test]
+    fn word_edits() {
+        assert_eq!(word_edit("def", "definitely"), (0, String::from("initely")));
+        assert_eq!(word_edit("café", "cafés"), (0, String::from("s")));
+        //a typo-tolerant pick replaces the typo instead of being appended to it
+        assert_eq!(word_edit("definat", "definitely"), (7, String::from("definitely")));
+        assert_eq!(word_edit("lon", "London"), (3, String::from("London")));
+    }
+
+    #[test]
+    fn replacement_edits() {
+        assert_eq!(replacement_edit("teh", "the"), (4, String::from("the ")));
+        //the punctuation after the word is on screen too, so it is deleted and put back after the correction
+        assert_eq!(replacement_edit("freinds'", with_trailing_punctuation("friends", "freinds'").as_str()), (9, String::from("friends' ")));
+        assert_eq!(with_trailing_punctuation("well", "wel--"), "well--");
+        assert_eq!(with_trailing_punctuation("the", "teh"), "the");
+    }
+
+    #[test]
+    fn text_before_cursor() {
+        assert!(typed_before_cursor("I saw teh ", 10, 10, "teh "));
+        assert!(typed_before_cursor("café teh cat", 9, 9, "teh "));
+        //the cursor was moved, or text selected, since the word was typed
+        assert!(!typed_before_cursor("I saw teh cat", 13, 13, "teh "));
+        assert!(!typed_before_cursor("teh cat", 4, 0, "teh "));
+        assert!(!typed_before_cursor("", 0, 0, "teh "));
+    }
+
+    //the word being typed after these keys, as the engine keeps track of it in Normal mode
+    unsafe fn typed_word(keyvals: &[guint]) -> String {
+        let mut word_buffer = String::new();
+        for keyval in keyvals.iter() {
+            match keyval_char(*keyval) {
+                Some(c) if is_word_char(c, word_buffer.as_str()) => word_buffer.push(c),
+                Some(_c) => word_buffer.clear(),
+                None if forgets_word(*keyval) => word_buffer.clear(),
+                None => {}
+            }
+        }
+        word_buffer
+    }
+
+    #[test]
+    fn navigation_ends_word() {
+        unsafe {
+            assert_eq!(typed_word(&[IBUS_f, IBUS_o, IBUS_o, IBUS_b, IBUS_a, IBUS_r]), "foobar");
+            //"foo" and "bar" typed in different places aren't learned as "foobar"
+            for navigation in [IBUS_Home, IBUS_End, IBUS_Up, IBUS_Delete, IBUS_Tab].iter() {
+                assert_eq!(typed_word(&[IBUS_f, IBUS_o, IBUS_o, *navigation, IBUS_b, IBUS_a, IBUS_r]), "bar");
+            }
+            //modifiers only change the next key
+            for modifier in [IBUS_Shift_L, IBUS_Control_L, IBUS_ISO_Level3_Shift].iter() {
+                assert_eq!(typed_word(&[IBUS_f, IBUS_o, IBUS_o, *modifier, IBUS_b, IBUS_a, IBUS_r]), "foobar");
+            }
+        }
+    }
+
+    #[test]
+    fn prediction_candidates() {
+        let mut alpha = Candidate::word(String::from("alpha"), 3, CandidateSource::Math);
+        alpha.commit = String::from("α");
+        alpha.description = Some(String::from("greek small letter alpha"));
+        let candidates = vec![
+            alpha,
+            Candidate::word(String::from("nul\0"), 2, CandidateSource::Dictionary),
+            Candidate::word(String::from("word"), 1, CandidateSource::User)
+        ];
+
+        let (len, candidates) = into_prediction_candidates(candidates);
+        unsafe {
+            let converted = std::slice::from_raw_parts(candidates, len as usize);
+            //the candidate with a nul can't be passed to C
+            assert_eq!(converted.len(), 2);
+            assert_eq!(CStr::from_ptr(converted[0].commit).to_str(), Ok("α"));
+            assert_eq!(CStr::from_ptr(converted[0].description).to_str(), Ok("greek small letter alpha"));
+            assert_eq!(converted[1].source, CandidateSource::User);
+            assert!(converted[1].description.is_null());
+            free_prediction_candidates(len, candidates);
+        }
+    }
+
+    unsafe fn commits(candidates: &[PredictionCandidate]) -> Vec<&str> {
+        candidates.iter().map(|candidate| CStr::from_ptr(candidate.commit).to_str().unwrap()).collect()
+    }
+
+    #[test]
+    fn predictions() {
+        let prefix = CString::new("definit").unwrap();
+        let query = CString::new("rocket").unwrap();
+        unsafe {
+            let words = predict_words(std::ptr::null(), prefix.as_ptr());
+            assert!(commits(std::slice::from_raw_parts((*words).candidates, (*words).len as usize)).contains(&"definitely"));
+            free_word_predictions(words);
+
+            let symbols = predict_symbols(query.as_ptr());
+            assert!(commits(std::slice::from_raw_parts((*symbols).candidates, (*symbols).len as usize)).contains(&"🚀"));
+            free_symbol_predictions(symbols);
+        }
+    }
+}
@@ -0,0 +1,144 @@
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{self, BufRead, Write};
+use std::path::PathBuf;
+use std::sync::{Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+use crate::user_data_dir;
+
+//What is stored for each key, as the tab separated fields following it on its line
+pub trait Record: Sized {
+    fn parse(fields: &[&str]) -> Option<Self>;
+    fn fields(&self) -> Vec<String>;
+}
+
+//Records kept in memory and persisted to a file in the user data dir, one "key\tfield\t..." line each
+pub struct PersistentTable<R> {
+    path: Option<PathBuf>,
+    records: HashMap<String, R>,
+    //records changed since the last save
+    dirty: bool,
+    last_save: Option<Instant>
+}
+
+impl<R: Record> PersistentTable<R> {
+    //records change while typing, so the file is rewritten at most this often; flush() saves the rest
+    const SAVE_INTERVAL: Duration = Duration::from_secs(30);
+
+    //a table that is never written to disk
+    pub fn in_memory() -> PersistentTable<R> {
+        PersistentTable {
+            path: None,
+            records: HashMap::new(),
+            dirty: false,
+            last_save: None
+        }
+    }
+
+    pub fn load(path: PathBuf) -> io::Result<PersistentTable<R>> {
+        let records = match File::open(&path) {
+            Ok(file) => {
+                io::BufReader::new(file)
+                    .lines()
+                    .collect::<Result<Vec<_>, _>>()?
+                    .iter()
+                    .filter_map(|line| {
+                        let record = parse_line(line.as_str());
+                        if record.is_none() {
+                            log::warn!("Skipping malformed line in {}: {}", path.display(), line);
+                        }
+                        record
+                    })
+                    .collect()
+            }
+            Err(err) if err.kind() == io::ErrorKind::NotFound => HashMap::new(),
+            Err(err) => return Err(err)
+        };
+
+        Ok(PersistentTable {
+            path: Some(path),
+            records,
+            dirty: false,
+            last_save: None
+        })
+    }
+
+    //the table in the user data dir, or one that is never saved if that can't be loaded
+    pub fn load_user_file(filename: &str) -> PersistentTable<R> {
+        match user_data_dir().map(|dir| PersistentTable::load(dir.join(filename))) {
+            Ok(Ok(table)) => table,
+            Ok(Err(err)) => {
+                log::error!("Failed to load {}, changes will not be saved: {}", filename, err);
+                PersistentTable::in_memory()
+            }
+            Err(err) => {
+                log::error!("Could not find user data directory, {} will not be saved: {}", filename, err);
+                PersistentTable::in_memory()
+            }
+        }
+    }
+
+    fn save(&self) -> io::Result<()> {
+        let path = match &self.path {
+            Some(path) => path,
+            None => return Ok(())
+        };
+
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        //write to a temporary file first so a crash never leaves a truncated file behind
+        let temp_path = path.with_extension("tmp");
+        let mut writer = io::BufWriter::new(File::create(&temp_path)?);
+        for (key, record) in self.records.iter() {
+            writeln!(writer, "{}\t{}", key, record.fields().join("\t"))?;
+        }
+        writer.flush()?;
+        fs::rename(temp_path, path)
+    }
+
+    pub fn records(&self) -> &HashMap<String, R> {
+        &self.records
+    }
+
+    //changes the records, saving them unless that was done recently
+    pub fn update<F: FnOnce(&mut HashMap<String, R>)>(&mut self, change: F) {
+        change(&mut self.records);
+        self.dirty = true;
+        let save_due = match self.last_save {
+            Some(last_save) => last_save.elapsed() >= PersistentTable::<R>::SAVE_INTERVAL,
+            None => true
+        };
+        if save_due {
+            self.flush();
+        }
+    }
+
+    //saves any changes since the last save
+    pub fn flush(&mut self) {
+        if !self.dirty {
+            return;
+        }
+        match self.save() {
+            Ok(()) => {
+                self.dirty = false;
+                self.last_save = Some(Instant::now());
+            }
+            Err(err) => {
+                log::error!("Failed to save {}: {}", self.path.as_ref().map_or(String::new(), |path| path.display().to_string()), err);
+            }
+        }
+    }
+}
+
+pub fn parse_line<R: Record>(line: &str) -> Option<(String, R)> {
+    let mut split_line = line.split('\t');
+    let key = split_line.next().filter(|key| !key.is_empty())?;
+    R::parse(&split_line.collect::<Vec<_>>()).map(|record| (String::from(key), record))
+}
+
+//a panic while holding the lock can't leave the records in an inconsistent state, so ignore poisoning
+pub fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+}
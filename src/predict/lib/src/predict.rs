@@ -1,26 +1,211 @@
-use fst::{Map, IntoStreamer};
-use fst::automaton::{Automaton, Str};
+use fst::{Map, IntoStreamer, Streamer};
+use fst::automaton::{Automaton, Str, Levenshtein};
 use lazy_static::lazy_static;
 use crate::predict::PredictionError::*;
+use crate::automaton::SegmentPrefix;
+use crate::backend::{SymbolPredictor, WordPredictor};
+use crate::candidate::{Candidate, CandidateSource};
+use crate::config::{config, Config, DictionaryConfig};
+use crate::data::{Data, data_dirs, find_data_file};
+use crate::emoji::{skin_tone_variants, supports_skin_tone, with_skin_tone, SkinTone};
+use crate::symbol_history::SymbolHistory;
+use crate::user_dictionary::UserDictionary;
+use std::cmp::{max, min};
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap, HashSet};
 use std::fmt;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, RwLock, TryLockError};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime};
+
+const DICTIONARY_FILE: &str = "dictionary.fst";
+const SHORTCODES_FILE: &str = "shortcodes.fst";
+const SYMBOLS_FILE: &str = "symbols.bin";
+const SYMBOL_NAMES_FILE: &str = "symbol_names.bin";
+const BIGRAMS_FILE: &str = "bigrams.fst";
+//CLDR annotation keywords, keyed by "keyword\x1fsymbol"
+const KEYWORDS_FILE: &str = "keywords.fst";
+//lowercased Unicode names of all named characters, keyed by "codepoint\x1fname" with six hex digit code points
+const CHARACTER_NAMES_FILE: &str = "character_names.fst";
+const KEY_SEPARATOR: char = '\x1f';
+//LaTeX command shortcodes keep their backslash, which keeps them apart from the other shortcodes
+const LATEX_PREFIX: char = '\\';
+const DATA_FILES: [&str; 6] = [SHORTCODES_FILE, SYMBOLS_FILE, SYMBOL_NAMES_FILE, BIGRAMS_FILE, KEYWORDS_FILE, CHARACTER_NAMES_FILE];
+
+static EMBEDDED_DICTIONARY: &[u8] = include_bytes!("../../dictionary.fst");
+static EMBEDDED_SHORTCODES: &[u8] = include_bytes!("../../shortcodes.fst");
+static EMBEDDED_SYMBOLS: &[u8] = include_bytes!("../../symbols.bin");
+static EMBEDDED_SYMBOL_NAMES: &[u8] = include_bytes!("../../symbol_names.bin");
+static EMBEDDED_BIGRAMS: &[u8] = include_bytes!("../../bigrams.fst");
+static EMBEDDED_KEYWORDS: &[u8] = include_bytes!("../../keywords.fst");
+static EMBEDDED_CHARACTER_NAMES: &[u8] = include_bytes!("../../character_names.fst");
+//the math symbols preproc picks from the Unicode math data, everything else in the symbol table is an emoji
+static MATH_WHITELIST: &str = include_str!("../../math_whitelist.txt");
+
+lazy_static! {
+    static ref MATH_SYMBOLS: HashSet<&'static str> = MATH_WHITELIST.lines().collect();
+}
+
+//A word list with frequencies, weighted against the other enabled dictionaries when merging results.
+//Words are stored lowercase, with the letters capitalised in the original word (e.g. "London", "NASA")
+//marked in the bits above the frequency.
+struct Dictionary {
+    name: String,
+    words: Map<Data>,
+    weight: f64
+}
+
+impl Dictionary {
+    fn new(config: &DictionaryConfig, data: Data) -> Result<Dictionary, PredictionError> {
+        Ok(Dictionary {
+            name: config.name.clone(),
+            words: Map::new(data).map_err(|err| DictionaryError(config.name.clone(), err))?,
+            weight: config.weight
+        })
+    }
+
+    const CASE_SHIFT: u32 = 48;
+    const FREQUENCY_MASK: u64 = (1 << Dictionary::CASE_SHIFT) - 1;
+    //capitals past this many characters into the word aren't kept
+    const CASE_BITS: usize = 64 - Dictionary::CASE_SHIFT as usize;
+
+    fn weighted_frequency(&self, value: u64) -> u64 {
+        ((value & Dictionary::FREQUENCY_MASK) as f64 * self.weight) as u64
+    }
+
+    //bit n is set if the nth character of the word is capitalised
+    fn case_mask(value: u64) -> u64 {
+        value >> Dictionary::CASE_SHIFT
+    }
+
+    //the default dictionary keeps its original filename so existing data packs still work
+    fn filename(name: &str) -> String {
+        if name == DictionaryConfig::DEFAULT_NAME {
+            String::from(DICTIONARY_FILE)
+        } else {
+            format!("dictionary_{}.fst", name)
+        }
+    }
+}
+
+//Keeps the best words streamed through it, only allocating for the ones good enough to be kept
+struct TopWords {
+    count: usize,
+    //how many words were pushed, kept or not
+    matches: usize,
+    //the worst kept word is on top: lowest frequency first, then alphabetically last
+    heap: BinaryHeap<Reverse<(u64, Reverse<String>)>>
+}
+
+impl TopWords {
+    fn new(count: usize) -> TopWords {
+        TopWords {
+            count,
+            matches: 0,
+            heap: BinaryHeap::with_capacity(count + 1)
+        }
+    }
+
+    fn push(&mut self, word: &str, freq: u64) {
+        self.matches += 1;
+        if self.heap.len() == self.count {
+            match self.heap.peek() {
+                Some(Reverse((worst_freq, Reverse(worst_word)))) => {
+                    if freq < *worst_freq || (freq == *worst_freq && word >= worst_word.as_str()) {
+                        return;
+                    }
+                }
+                None => return
+            }
+            self.heap.pop();
+        }
+        self.heap.push(Reverse((freq, Reverse(String::from(word)))));
+    }
+
+    fn into_vec(self) -> Vec<(String, u64)> {
+        self.heap.into_iter().map(|Reverse((freq, Reverse(word)))| (word, freq)).collect()
+    }
+}
+
+struct Correction {
+    word: String,
+    freq: u64,
+    distance: u32,
+    //two neighbouring letters swapped, counted as a single edit
+    transposition: bool
+}
+
+//How sure autocorrect has to be before replacing a word without asking
+#[derive(Debug, Clone, PartialEq)]
+pub struct AutocorrectConfidence {
+    //corrections more typos away than this are only ever offered
+    pub max_distance: u32,
+    //words rarer than this are never corrected to, so jargon isn't replaced by a lookalike
+    pub min_frequency: u64,
+    //how many times more likely the correction must be than the next best one
+    pub min_lead: f64,
+    //swapped letters are a common typo, so they count as this many times more likely
+    pub transposition_weight: f64
+}
+
+impl Default for AutocorrectConfidence {
+    fn default() -> AutocorrectConfidence {
+        AutocorrectConfidence {
+            max_distance: 1,
+            min_frequency: 1_000_000,
+            min_lead: 10.0,
+            transposition_weight: 4.0
+        }
+    }
+}
 
 pub struct Predictor {
-    dictionary: Map<Vec<u8>>,
-    shortcode_dictionary: Map<Vec<u8>>,
-    symbols: Vec<String>
+    dictionaries: Vec<Dictionary>,
+    //the best words for prefixes up to CACHED_PREFIX_LEN characters that match many words, merged over all dictionaries
+    prefix_cache: HashMap<String, Vec<(String, u64)>>,
+    shortcode_dictionary: Map<Data>,
+    symbols: Vec<String>,
+    //lowercased official Unicode names, in the same order as the symbols
+    symbol_names: Vec<String>,
+    keywords: Map<Data>,
+    character_names: Map<Data>,
+    bigrams: Map<Data>,
+    max_edit_distance: u32,
+    skin_tone: Option<SkinTone>
+}
+
+//the files preproc writes for the symbol table
+struct SymbolData {
+    shortcodes: Data,
+    symbols: Data,
+    symbol_names: Data,
+    keywords: Data,
+    character_names: Data
 }
 
 #[derive(Debug)]
 pub enum PredictionError {
     FstError(fst::Error),
+    DictionaryError(String, fst::Error),
+    LevenshteinError(fst::automaton::LevenshteinError),
     MissingSymbol(String, u64),
+    IoError(PathBuf, io::Error),
+    DecodeError(bincode::Error),
 }
 
 impl fmt::Display for PredictionError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
             FstError(err) => write!(f, "FST error: {}", err),
+            DictionaryError(name, err) => write!(f, "FST error in dictionary {}: {}", name, err),
+            LevenshteinError(err) => write!(f, "Levenshtein automaton error: {}", err),
             MissingSymbol(sym, codepoint) => write!(f, "Missing shortcode: {}, for codepoint {}", sym, codepoint),
+            IoError(path, err) => write!(f, "Failed to read {}: {}", path.display(), err),
+            DecodeError(err) => write!(f, "Failed to decode symbols: {}", err),
         }
     }
 }
@@ -28,76 +213,927 @@ impl fmt::Display for PredictionError {
 
 impl Predictor {
     const WORD_COUNT: usize = 25;
+    //every this many characters of input allow one more typo, so short prefixes stay exact
+    const CHARS_PER_EDIT: u32 = 3;
+    //a single use of a word counts for as much as this many occurrences in the frequency data
+    const USER_COUNT_WEIGHT: u64 = 10_000_000;
+    //shorter queries would match part of nearly every symbol name
+    const MIN_NAME_QUERY_LEN: usize = 3;
+    const MIN_KEYWORD_QUERY_LEN: usize = 2;
+    //short prefixes can match a large part of the dictionary, so the best words for those that match
+    //more than CACHE_MIN_MATCHES words are worked out once when loading instead of on every keystroke
+    const CACHED_PREFIX_LEN: usize = 3;
+    const CACHE_MIN_MATCHES: usize = 100;
+
+    //files found in the data directories take precedence over the copies compiled into the engine
+    fn load_from(dirs: &[PathBuf], config: &Config) -> Result<Predictor, PredictionError> {
+        let mut dictionaries = Vec::new();
+        for dictionary_config in config.dictionaries.iter() {
+            let filename = Dictionary::filename(dictionary_config.name.as_str());
+            let data = if dictionary_config.name == DictionaryConfig::DEFAULT_NAME {
+                Some(Predictor::load_data(dirs, filename.as_str(), EMBEDDED_DICTIONARY)?)
+            } else {
+                Predictor::load_optional_data(dirs, filename.as_str())?
+            };
+
+            match data {
+                Some(data) => dictionaries.push(Dictionary::new(dictionary_config, data)?),
+                None => log::warn!("Dictionary {} is enabled but {} was not found", dictionary_config.name, filename)
+            }
+        }
+
+        Predictor::from_data(
+            dictionaries,
+            SymbolData {
+                shortcodes: Predictor::load_data(dirs, SHORTCODES_FILE, EMBEDDED_SHORTCODES)?,
+                symbols: Predictor::load_data(dirs, SYMBOLS_FILE, EMBEDDED_SYMBOLS)?,
+                symbol_names: Predictor::load_data(dirs, SYMBOL_NAMES_FILE, EMBEDDED_SYMBOL_NAMES)?,
+                keywords: Predictor::load_data(dirs, KEYWORDS_FILE, EMBEDDED_KEYWORDS)?,
+                character_names: Predictor::load_data(dirs, CHARACTER_NAMES_FILE, EMBEDDED_CHARACTER_NAMES)?
+            },
+            Predictor::load_data(dirs, BIGRAMS_FILE, EMBEDDED_BIGRAMS)?,
+            config
+        )
+    }
+
+    pub fn embedded(config: &Config) -> Result<Predictor, PredictionError> {
+        let default_dictionary = DictionaryConfig {
+            name: String::from(DictionaryConfig::DEFAULT_NAME),
+            weight: 1.0
+        };
+
+        Predictor::from_data(
+            vec![Dictionary::new(&default_dictionary, Data::Embedded(EMBEDDED_DICTIONARY))?],
+            SymbolData {
+                shortcodes: Data::Embedded(EMBEDDED_SHORTCODES),
+                symbols: Data::Embedded(EMBEDDED_SYMBOLS),
+                symbol_names: Data::Embedded(EMBEDDED_SYMBOL_NAMES),
+                keywords: Data::Embedded(EMBEDDED_KEYWORDS),
+                character_names: Data::Embedded(EMBEDDED_CHARACTER_NAMES)
+            },
+            Data::Embedded(EMBEDDED_BIGRAMS),
+            config
+        )
+    }
+
+    fn from_data(dictionaries: Vec<Dictionary>, symbol_data: SymbolData, bigrams: Data, config: &Config) -> Result<Predictor, PredictionError> {
+        log::info!("Using dictionaries {:?}", dictionaries.iter().map(|dictionary| dictionary.name.as_str()).collect::<Vec<_>>());
+        let symbols: Vec<String> = bincode::deserialize(symbol_data.symbols.as_ref()).map_err(DecodeError)?;
+        let mut symbol_names: Vec<String> = bincode::deserialize(symbol_data.symbol_names.as_ref()).map_err(DecodeError)?;
+        if symbol_names.len() != symbols.len() {
+            //names from a different build of the data would be attached to the wrong symbols
+            log::warn!("Ignoring symbol names, found {} names for {} symbols", symbol_names.len(), symbols.len());
+            symbol_names.clear();
+        }
+
+        Ok(Predictor {
+            prefix_cache: Predictor::build_prefix_cache(&dictionaries)?,
+            dictionaries,
+            shortcode_dictionary: Map::new(symbol_data.shortcodes).map_err(FstError)?,
+            symbols,
+            symbol_names: symbol_names.iter().map(|name| name.to_lowercase()).collect(),
+            keywords: Map::new(symbol_data.keywords).map_err(FstError)?,
+            character_names: Map::new(symbol_data.character_names).map_err(FstError)?,
+            bigrams: Map::new(bigrams).map_err(FstError)?,
+            max_edit_distance: config.max_edit_distance,
+            skin_tone: config.skin_tone
+        })
+    }
+
+    //nothing to predict from, for when not even the built in data can be loaded
+    fn empty(config: &Config) -> Predictor {
+        //fst's own empty map, which it builds the same way, so converting it can't fail
+        let empty_map = || Map::default().map_data(Data::Owned).expect("empty map is valid");
+        Predictor {
+            dictionaries: Vec::new(),
+            prefix_cache: HashMap::new(),
+            shortcode_dictionary: empty_map(),
+            symbols: Vec::new(),
+            symbol_names: Vec::new(),
+            keywords: empty_map(),
+            character_names: empty_map(),
+            bigrams: empty_map(),
+            max_edit_distance: config.max_edit_distance,
+            skin_tone: config.skin_tone
+        }
+    }
+
+    fn load_data(dirs: &[PathBuf], filename: &str, embedded: &'static [u8]) -> Result<Data, PredictionError> {
+        Predictor::load_optional_data(dirs, filename).map(|data| data.unwrap_or(Data::Embedded(embedded)))
+    }
+
+    fn load_optional_data(dirs: &[PathBuf], filename: &str) -> Result<Option<Data>, PredictionError> {
+        match find_data_file(dirs, filename) {
+            Some(path) => {
+                log::info!("Loading {} from {}", filename, path.display());
+                //read rather than mapped, so a data pack rewritten while the engine runs can't pull the data out from under it
+                fs::read(&path)
+                    .map(|bytes| Some(Data::Owned(bytes)))
+                    .map_err(|err| IoError(path, err))
+            }
+            None => Ok(None)
+        }
+    }
+
+    //runs the search over every enabled dictionary, keeping the best weighted frequency for each word
+    fn search_dictionaries<A: Automaton>(&self, matcher: A) -> Result<Vec<(String, u64)>, PredictionError> {
+        let mut merged_results: HashMap<String, u64> = HashMap::new();
+        for dictionary in self.dictionaries.iter() {
+            let search_results = dictionary.words.search(&matcher)
+                .into_stream()
+                .into_str_vec().map_err(FstError)?;
+
+            for (word, freq) in search_results {
+                let weighted_freq = dictionary.weighted_frequency(freq);
+                let best_freq = merged_results.entry(word).or_insert(weighted_freq);
+                *best_freq = std::cmp::max(*best_freq, weighted_freq);
+            }
+        }
+        Ok(merged_results.into_iter().collect())
+    }
+
+    //keeps the best weighted frequency for each word, and the WORD_COUNT best words of those
+    fn merge_best_words(results: impl Iterator<Item=(String, u64)>) -> Vec<(String, u64)> {
+        let mut merged_results: HashMap<String, u64> = HashMap::new();
+        for (word, freq) in results {
+            let best_freq = merged_results.entry(word).or_insert(freq);
+            *best_freq = std::cmp::max(*best_freq, freq);
+        }
+        let mut best_words = merged_results.into_iter().collect::<Vec<_>>();
+        best_words.sort_by(|(w1, f1), (w2, f2)| f2.cmp(f1).then(w1.cmp(w2)));
+        best_words.truncate(Predictor::WORD_COUNT);
+        best_words
+    }
+
+    fn build_prefix_cache(dictionaries: &[Dictionary]) -> Result<HashMap<String, Vec<(String, u64)>>, PredictionError> {
+        //match count and best words from each dictionary for every prefix
+        let mut dictionary_results: HashMap<String, (usize, Vec<(String, u64)>)> = HashMap::new();
+        for dictionary in dictionaries.iter() {
+            let mut top_words: HashMap<String, TopWords> = HashMap::new();
+            let mut stream = dictionary.words.stream();
+            while let Some((key, freq)) = stream.next() {
+                let word = match std::str::from_utf8(key) {
+                    Ok(word) => word,
+                    Err(_) => continue
+                };
+                let weighted_freq = dictionary.weighted_frequency(freq);
+                let prefix_ends = std::iter::once(0)
+                    .chain(word.char_indices().map(|(idx, c)| idx + c.len_utf8()))
+                    .take(Predictor::CACHED_PREFIX_LEN + 1);
+                for prefix_end in prefix_ends {
+                    let prefix = &word[..prefix_end];
+                    match top_words.get_mut(prefix) {
+                        Some(prefix_words) => prefix_words.push(word, weighted_freq),
+                        None => {
+                            let mut prefix_words = TopWords::new(Predictor::WORD_COUNT);
+                            prefix_words.push(word, weighted_freq);
+                            top_words.insert(String::from(prefix), prefix_words);
+                        }
+                    }
+                }
+            }
+
+            for (prefix, prefix_words) in top_words {
+                let (matches, results) = dictionary_results.entry(prefix).or_default();
+                *matches += prefix_words.matches;
+                results.extend(prefix_words.into_vec());
+            }
+        }
+
+        //a word in the overall top WORD_COUNT is always in the top WORD_COUNT of the dictionary giving it
+        //its best frequency, so merging each dictionary's best words loses nothing
+        Ok(dictionary_results.into_iter()
+            .filter(|(_prefix, (matches, _results))| *matches > Predictor::CACHE_MIN_MATCHES)
+            .map(|(prefix, (_matches, results))| (prefix, Predictor::merge_best_words(results.into_iter())))
+            .collect())
+    }
+
+    //the WORD_COUNT most frequent words starting with the prefix, best first
+    fn top_dictionary_words(&self, prefix: &str) -> Result<Vec<(String, u64)>, PredictionError> {
+        if let Some(cached) = self.prefix_cache.get(prefix) {
+            return Ok(cached.clone());
+        }
+
+        let matcher = Str::new(prefix).starts_with();
+        let mut results = Vec::new();
+        for dictionary in self.dictionaries.iter() {
+            let mut top_words = TopWords::new(Predictor::WORD_COUNT);
+            let mut stream = dictionary.words.search(&matcher).into_stream();
+            while let Some((key, freq)) = stream.next() {
+                if let Ok(word) = std::str::from_utf8(key) {
+                    top_words.push(word, dictionary.weighted_frequency(freq));
+                }
+            }
+            results.extend(top_words.into_vec());
+        }
+        Ok(Predictor::merge_best_words(results.into_iter()))
+    }
+
+    //best weighted frequency of the word over all dictionaries, 0 if none has it
+    fn frequency(&self, word: &str) -> u64 {
+        self.dictionaries.iter()
+            .filter_map(|dictionary| dictionary.words.get(word).map(|freq| dictionary.weighted_frequency(freq)))
+            .max()
+            .unwrap_or(0)
+    }
 
     fn is_title_cased(context: &str) -> bool {
         let mut chars = context.chars();
 
-        let first_letter_capitalized = chars.next().map(|char| char.is_ascii_uppercase()).unwrap_or(false);
-        let other_capitals: i8 = chars.map(|char| char.is_ascii_uppercase() as i8).sum();
+        let first_letter_capitalized = chars.next().map(|char| char.is_uppercase()).unwrap_or(false);
+        let other_capitals = chars.filter(|char| char.is_uppercase()).count();
         first_letter_capitalized & (other_capitals == 0)
     }
 
+    fn is_all_caps(context: &str) -> bool {
+        let uppercase = context.chars().filter(|char| char.is_uppercase()).count();
+        uppercase >= 2 && !context.chars().any(|char| char.is_lowercase())
+    }
+
     fn title_case(word: String) -> String {
         let mut chars = word.chars();
-        chars.next().unwrap().to_ascii_uppercase().to_string() + chars.as_str()
+        match chars.next() {
+            Some(first) => first.to_uppercase().collect::<String>() + chars.as_str(),
+            None => word
+        }
     }
 
-    pub fn word(&self, context: &str) -> Result<Vec<String>,  PredictionError>  {
-        let title_cased = Predictor::is_title_cased(context);
-        let lowercase_context = context.to_ascii_lowercase();
-        let matcher = Str::new(lowercase_context.as_str()).starts_with();
+    //the form words are stored in by preproc and the user dictionary
+    pub fn fold_case(word: &str) -> String {
+        word.to_lowercase()
+    }
 
-        let mut search_results = self.dictionary.search(matcher)
-            .into_stream()
-            .into_str_vec().map_err(FstError)?;
+    //how the dictionaries capitalise the word, e.g. "London" for "london". Words any dictionary has in
+    //lowercase, like "may", stay lowercase.
+    fn canonical_case(&self, word: String) -> String {
+        let masks = self.dictionaries.iter()
+            .filter_map(|dictionary| dictionary.words.get(word.as_str()))
+            .map(Dictionary::case_mask)
+            .collect::<Vec<_>>();
+        match masks.first() {
+            Some(mask) if !masks.contains(&0) => {
+                word.chars()
+                    .enumerate()
+                    .map(|(idx, char)| {
+                        if idx < Dictionary::CASE_BITS && (mask >> idx) & 1 == 1 {
+                            char.to_uppercase().collect::<String>()
+                        } else {
+                            char.to_string()
+                        }
+                    })
+                    .collect()
+            }
+            _ => word
+        }
+    }
+
+    //capitalises a lowercase result to match what was typed: "NAS" gives "NASA", "Lon" and "lon" both
+    //give "London", and "Th" gives "The"
+    fn match_case(&self, word: String, context: &str) -> String {
+        let word = self.canonical_case(word);
+        if Predictor::is_all_caps(context) {
+            word.to_uppercase()
+        } else if Predictor::is_title_cased(context) && !word.chars().any(|char| char.is_uppercase()) {
+            Predictor::title_case(word)
+        } else {
+            word
+        }
+    }
+
+    fn allowed_edit_distance(context: &str, max_edit_distance: u32) -> u32 {
+        let length = context.chars().count() as u32;
+        min(max_edit_distance, length.saturating_sub(1) / Predictor::CHARS_PER_EDIT)
+    }
+
+    //smallest edit distance between the query and any prefix of the word, and the distance to the whole word
+    fn edit_distances(query: &str, word: &str) -> (u32, u32) {
+        let query: Vec<char> = query.chars().collect();
+        let mut row: Vec<u32> = (0..=query.len() as u32).collect();
+        let mut best = row[query.len()];
+
+        for word_char in word.chars() {
+            let mut diagonal = row[0];
+            row[0] += 1;
+            for (idx, query_char) in query.iter().enumerate() {
+                let above = row[idx + 1];
+                row[idx + 1] = min(min(above, row[idx]) + 1, diagonal + (*query_char != word_char) as u32);
+                diagonal = above;
+            }
+            best = min(best, row[query.len()]);
+        }
+        (best, row[query.len()])
+    }
+
+    fn fuzzy_word_matches(&self, query: &str, distance: u32) -> Result<Vec<(String, u64)>, PredictionError> {
+        let matcher = Levenshtein::new(query, distance).map_err(LevenshteinError)?.starts_with();
+        let mut search_results = self.search_dictionaries(matcher)?
+            .into_iter()
+            .filter(|(word, _freq)| !word.starts_with(query))
+            .map(|(word, freq)| (Predictor::edit_distances(query, word.as_str()).0, word, freq))
+            .collect::<Vec<_>>();
+
+        search_results.sort_by(|(d1, w1, f1), (d2, w2, f2)| d1.cmp(d2).then(f2.cmp(f1)).then(w1.cmp(w2)));
+        Ok(search_results.into_iter().map(|(_distance, word, freq)| (word, freq)).collect())
+    }
+
+    pub fn contains(&self, word: &str) -> bool {
+        self.dictionaries.iter().any(|dictionary| dictionary.words.contains_key(word))
+    }
+
+    pub fn word(&self, context: &str, user_dictionary: &UserDictionary) -> Result<Vec<Candidate>,  PredictionError>  {
+        self.word_with_distance(context, user_dictionary, self.max_edit_distance)
+    }
+
+    pub fn word_with_distance(&self, context: &str, user_dictionary: &UserDictionary, max_edit_distance: u32) -> Result<Vec<Candidate>,  PredictionError>  {
+        let lowercase_context = Predictor::fold_case(context);
+
+        //learned words are boosted by how often they were used, and ones that aren't in the static
+        //dictionary are ranked purely on that
+        let mut scores: HashMap<String, u64> = self.top_dictionary_words(lowercase_context.as_str())?
+            .into_iter()
+            .collect();
+        for (word, count) in user_dictionary.prefix_matches(lowercase_context.as_str()) {
+            scores.insert(word.clone(), self.frequency(word.as_str()) + count * Predictor::USER_COUNT_WEIGHT);
+        }
+        let mut search_results = scores.into_iter().collect::<Vec<_>>();
+
+        //ties are broken alphabetically, since merging loses the FST order
+        search_results.sort_by(|(w1, f1), (w2, f2)| f2.cmp(f1).then(w1.cmp(w2)));
 
-        search_results.sort_by(|(_w1, f1), (_w2, f2)| f2.cmp(f1));
-        let final_results = search_results
+        //the last part of a hyphenated compound like "well-kn" is completed on its own, typos included
+        let compound_split = context.rfind('-');
+
+        //exact prefix matches always rank first, typo matches only fill the remaining slots
+        let distance = Predictor::allowed_edit_distance(context, max_edit_distance);
+        if search_results.len() < Predictor::WORD_COUNT && distance > 0 && compound_split.is_none() {
+            search_results.extend(self.fuzzy_word_matches(lowercase_context.as_str(), distance)?);
+        }
+
+        let mut final_results = search_results
             .into_iter()
-            .map(|(word, _freq)| {
-                if title_cased {
-                    Predictor::title_case(word)
+            .map(|(word, freq)| {
+                let source = if user_dictionary.count(word.as_str()) > 0 {
+                    CandidateSource::User
                 } else {
-                    word
-                }
+                    CandidateSource::Dictionary
+                };
+                Candidate::word(self.match_case(word, context), freq, source)
             })
-            .take(Predictor::WORD_COUNT).collect();
+            .take(Predictor::WORD_COUNT).collect::<Vec<_>>();
+
+        if let Some(split) = compound_split {
+            let (head, tail) = (&context[..=split], &context[split + 1..]);
+            if !tail.is_empty() {
+                let compounds = self.word_with_distance(tail, user_dictionary, max_edit_distance)?
+                    .into_iter()
+                    .map(|candidate| Candidate::word(format!("{}{}", head, candidate.commit), candidate.score, candidate.source))
+                    .filter(|compound| !final_results.iter().any(|candidate| candidate.commit == compound.commit))
+                    .collect::<Vec<_>>();
+                final_results.extend(compounds);
+                final_results.truncate(Predictor::WORD_COUNT);
+            }
+        }
         Ok(final_results)
     }
 
-    pub fn symbol(&self, context: &str) -> Result<Vec<(String, String)>,  PredictionError> {
+    //dictionary words a typo or two away from a word that isn't in any dictionary, closest and then
+    //most frequent first. Short words get one typo, so "teh" can still be corrected.
+    fn ranked_corrections(&self, lowercase_word: &str) -> Result<Vec<Correction>, PredictionError> {
+        let distance = min(max(1, Predictor::allowed_edit_distance(lowercase_word, self.max_edit_distance)), self.max_edit_distance);
+        if distance == 0 {
+            return Ok(Vec::new());
+        }
+
+        let matcher = Levenshtein::new(lowercase_word, distance).map_err(LevenshteinError)?;
+        let mut corrections = self.search_dictionaries(matcher)?
+            .into_iter()
+            .map(|(word, freq)| Correction {
+                distance: Predictor::edit_distances(lowercase_word, word.as_str()).1,
+                transposition: false,
+                word,
+                freq
+            })
+            .collect::<Vec<_>>();
+
+        //swapped letters are the most common typo, but take two edits for the automaton, so they're
+        //looked up separately and count as one
+        let chars = lowercase_word.chars().collect::<Vec<_>>();
+        for idx in 1..chars.len() {
+            let mut swapped = chars.clone();
+            swapped.swap(idx - 1, idx);
+            let swapped = swapped.into_iter().collect::<String>();
+            if swapped != lowercase_word && self.contains(swapped.as_str()) {
+                corrections.retain(|correction| correction.word != swapped);
+                corrections.push(Correction {
+                    freq: self.frequency(swapped.as_str()),
+                    word: swapped,
+                    distance: 1,
+                    transposition: true
+                });
+            }
+        }
+        corrections.sort_by(|c1, c2| c1.distance.cmp(&c2.distance).then(c2.freq.cmp(&c1.freq)).then(c1.word.cmp(&c2.word)));
+        Ok(corrections)
+    }
+
+    pub fn corrections(&self, word: &str) -> Result<Vec<Candidate>, PredictionError> {
+        Ok(self.ranked_corrections(Predictor::fold_case(word).as_str())?
+            .into_iter()
+            .map(|correction| Candidate::word(self.match_case(correction.word, word), correction.freq, CandidateSource::Dictionary))
+            .take(Predictor::WORD_COUNT)
+            .collect())
+    }
+
+    //the correction to make without asking, if one is far more likely than any other
+    pub fn autocorrection(&self, word: &str, confidence: &AutocorrectConfidence) -> Result<Option<Candidate>, PredictionError> {
+        let mut scores = self.ranked_corrections(Predictor::fold_case(word).as_str())?
+            .into_iter()
+            .filter(|correction| correction.distance <= confidence.max_distance)
+            .map(|correction| {
+                let weight = if correction.transposition { confidence.transposition_weight } else { 1.0 };
+                (correction.freq as f64 * weight, correction)
+            })
+            .collect::<Vec<_>>();
+        scores.sort_by(|(s1, _c1), (s2, _c2)| s2.partial_cmp(s1).unwrap_or(std::cmp::Ordering::Equal));
+
+        let mut scores = scores.into_iter();
+        Ok(match (scores.next(), scores.next()) {
+            (Some((best_score, best)), runner_up) => {
+                let clear_lead = match runner_up {
+                    Some((runner_up_score, _runner_up)) => best_score >= runner_up_score * confidence.min_lead,
+                    None => true
+                };
+                if clear_lead && best.freq >= confidence.min_frequency {
+                    Some(Candidate::word(self.match_case(best.word, word), best.freq, CandidateSource::Dictionary))
+                } else {
+                    None
+                }
+            }
+            (None, _) => None
+        })
+    }
+
+    pub fn next_word(&self, previous_word: &str, context: &str) -> Result<Vec<Candidate>, PredictionError> {
+        //bigram keys are the two words separated by a space
+        let previous_word = Predictor::fold_case(previous_word);
+        let search_prefix = format!("{} {}", previous_word, Predictor::fold_case(context));
+        let matcher = Str::new(search_prefix.as_str()).starts_with();
+
+        let mut search_results = self.bigrams.search(matcher)
+            .into_stream()
+            .into_str_vec().map_err(FstError)?;
+
+        search_results.sort_by(|(_b1, c1), (_b2, c2)| c2.cmp(c1));
+        Ok(search_results
+            .into_iter()
+            .map(|(bigram, count)| {
+                let word = String::from(&bigram[previous_word.len() + 1..]);
+                Candidate::word(self.match_case(word, context), count, CandidateSource::Dictionary)
+            })
+            .take(Predictor::WORD_COUNT)
+            .collect())
+    }
+
+    //every word of the query has to start one of the words in the name
+    fn name_matches(name: &str, query_words: &[&str]) -> bool {
+        let name_words = name.split([' ', '-', ','])
+            .filter(|word| !word.is_empty())
+            .collect::<Vec<_>>();
+        query_words.iter().all(|query_word| name_words.iter().any(|name_word| name_word.starts_with(query_word)))
+    }
+
+    fn symbol_name_matches(&self, context: &str, exclude: &HashSet<u64>) -> Vec<(String, u64)> {
+        let lowercase_context = context.to_lowercase();
+        let query_words = lowercase_context.split(|c: char| c.is_whitespace() || c == '_')
+            .filter(|word| !word.is_empty())
+            .collect::<Vec<_>>();
+        if context.len() < Predictor::MIN_NAME_QUERY_LEN || query_words.is_empty() {
+            return Vec::new();
+        }
+
+        let mut name_results = self.symbol_names.iter().enumerate()
+            .map(|(ident, name)| (name, ident as u64))
+            .filter(|(name, ident)| !exclude.contains(ident) && Predictor::name_matches(name, &query_words))
+            .map(|(name, ident)| (name.clone(), ident))
+            .collect::<Vec<_>>();
+        name_results.sort_by(|(n1, _i1), (n2, _i2)| n1.len().cmp(&n2.len()).then(n1.cmp(n2)));
+        name_results
+    }
+
+    //symbols with an annotation keyword (or a word of one) starting with the context, labelled with that keyword
+    fn keyword_matches(&self, context: &str, exclude: &HashSet<u64>) -> Result<Vec<(String, u64)>, PredictionError> {
+        if context.len() < Predictor::MIN_KEYWORD_QUERY_LEN {
+            return Ok(Vec::new());
+        }
+
+        let lowercase_context = context.to_lowercase();
+        let mut keyword_results = self.keywords.search(SegmentPrefix::new(lowercase_context.as_str()))
+            .into_stream()
+            .into_str_vec().map_err(FstError)?
+            .into_iter()
+            .filter_map(|(key, ident)| {
+                key.split_once(KEY_SEPARATOR).map(|(keyword, _symbol)| (keyword.replace('_', " "), ident))
+            })
+            .collect::<Vec<_>>();
+        keyword_results.sort_by(|(k1, _i1), (k2, _i2)| k1.len().cmp(&k2.len()).then(k1.cmp(k2)));
+
+        let mut found = exclude.clone();
+        keyword_results.retain(|(_keyword, ident)| found.insert(*ident));
+        Ok(keyword_results)
+    }
+
+    fn symbol_source(symbol: &str) -> CandidateSource {
+        if MATH_SYMBOLS.contains(symbol) {
+            CandidateSource::Math
+        } else {
+            CandidateSource::Emoji
+        }
+    }
+
+    //emoji are shown in the configured skin tone, which is also how they end up in the history,
+    //and the score is how often the symbol was used
+    fn symbol_candidate(&self, label: String, ident: u64, history: &SymbolHistory) -> Result<Candidate, PredictionError> {
+        let symbol = match self.symbols.get(ident as usize) {
+            Some(symbol) => symbol,
+            None => return Err(MissingSymbol(label, ident))
+        };
+        let description = self.symbol_names.get(ident as usize)
+            .filter(|name| !name.is_empty() && **name != label)
+            .cloned();
+        let commit = match self.skin_tone {
+            Some(tone) if supports_skin_tone(symbol) => with_skin_tone(symbol, tone),
+            _ => symbol.clone()
+        };
+        let source = if label.starts_with(LATEX_PREFIX) {
+            CandidateSource::Math
+        } else {
+            Predictor::symbol_source(symbol)
+        };
+
+        Ok(Candidate {
+            display: label,
+            score: history.count(commit.as_str()),
+            commit,
+            source,
+            description
+        })
+    }
+
+    pub fn symbol(&self, context: &str, history: &SymbolHistory) -> Result<Vec<Candidate>,  PredictionError> {
+        //names are typed with spaces, but shortcodes use underscores instead
+        let shortcode_context = context.replace(' ', "_");
+        let context = shortcode_context.as_str();
         let matcher = Str::new(context).starts_with();
-        let search_results  = self.shortcode_dictionary.search(matcher)
+        let mut search_results  = self.shortcode_dictionary.search(matcher)
             .into_stream()
             .into_str_vec().map_err(FstError)?;
 
+        //a LaTeX command only finds other LaTeX commands, and only ones starting with it
+        if !context.starts_with(LATEX_PREFIX) {
+            //shortcodes with a later segment starting with the context come after all the prefix matches,
+            //shortest first since those are the closest to what was typed
+            let mut segment_results = self.shortcode_dictionary.search(SegmentPrefix::new(context))
+                .into_stream()
+                .into_str_vec().map_err(FstError)?
+                .into_iter()
+                .filter(|(shortcode, _ident)| !shortcode.starts_with(context) && !shortcode.starts_with(LATEX_PREFIX))
+                .collect::<Vec<_>>();
+            segment_results.sort_by(|(s1, _i1), (s2, _i2)| s1.len().cmp(&s2.len()).then(s1.cmp(s2)));
+            search_results.extend(segment_results);
+
+            //then symbols found by annotation keyword, and last by official name, each only if nothing
+            //earlier already found them
+            let mut found = search_results.iter().map(|(_shortcode, ident)| *ident).collect::<HashSet<_>>();
+            let keyword_results = self.keyword_matches(context, &found)?;
+            found.extend(keyword_results.iter().map(|(_keyword, ident)| *ident));
+            search_results.extend(keyword_results);
+            search_results.extend(self.symbol_name_matches(shortcode_context.as_str(), &found));
+        }
+
         //must be into_iter() and not iter() - the latter iterates over references, but we need
         //to take ownership to return the shortcode data without clone()
-        Ok(search_results.into_iter().map(|(shortcode, ident)| {
-            match self.symbols.get(ident as usize) {
-                Some(symbol) => Ok((shortcode, symbol.clone())),
-                None => Err(MissingSymbol(shortcode, ident))
-            }
-        }).collect::<Result<Vec<_>, _>>()?)
+        let mut symbol_results = search_results.into_iter()
+            .map(|(label, ident)| self.symbol_candidate(label, ident, history))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        //symbols used before move to the front, most used first; the stable sort keeps the order of the rest
+        symbol_results.sort_by_key(|candidate| Reverse(candidate.score));
+        Ok(symbol_results)
+    }
+
+    //other emoji whose shortcode only differs by gender, e.g. biking_woman for biking_man
+    fn gender_variants(&self, shortcode: &str) -> Vec<(String, String)> {
+        let segments = shortcode.split('_').collect::<Vec<_>>();
+        segments.iter().enumerate().filter_map(|(idx, segment)| {
+            let swapped = match *segment {
+                "man" => "woman",
+                "woman" => "man",
+                "men" => "women",
+                "women" => "men",
+                _ => return None
+            };
+            let mut variant_segments = segments.clone();
+            variant_segments[idx] = swapped;
+            let variant = variant_segments.join("_");
+            self.shortcode_dictionary.get(variant.as_str())
+                .and_then(|ident| self.symbols.get(ident as usize))
+                .map(|symbol| (variant, symbol.clone()))
+        }).collect()
     }
+
+    //the symbol and its gender variants, each followed by its skin tones if it has any
+    pub fn symbol_variants(&self, shortcode: &str, symbol: &str) -> Vec<Candidate> {
+        let mut variants = vec![(String::from(shortcode), String::from(symbol))];
+        variants.extend(self.gender_variants(shortcode));
+        variants.iter()
+            .flat_map(|(shortcode, symbol)| skin_tone_variants(shortcode, symbol))
+            .map(|(shortcode, symbol)| Candidate {
+                source: Predictor::symbol_source(symbol.as_str()),
+                display: shortcode,
+                commit: symbol,
+                score: 0,
+                description: None
+            })
+            .collect()
+    }
+
+    //the symbols offered before anything is typed, from the usage history
+    pub fn symbol_suggestions(&self, history: &SymbolHistory, limit: usize) -> Vec<Candidate> {
+        history.suggestions(limit).into_iter()
+            .map(|(shortcode, symbol)| Candidate {
+                source: Predictor::symbol_source(symbol.as_str()),
+                score: history.count(symbol.as_str()),
+                display: shortcode,
+                commit: symbol,
+                description: None
+            })
+            .collect()
+    }
+
+    //the lowercased official Unicode name of a character, e.g. "latin small letter e with acute" for é
+    pub fn character_name(&self, c: char) -> Option<String> {
+        let prefix = format!("{:06X}{}", c as u32, KEY_SEPARATOR);
+        let mut stream = self.character_names.range().ge(prefix.as_str()).into_stream();
+        stream.next()
+            .and_then(|(key, _codepoint)| key.strip_prefix(prefix.as_bytes()))
+            .and_then(|name| std::str::from_utf8(name).ok())
+            .map(String::from)
+    }
+}
+
+//Dictionary completions, with the user's own words mixed in
+pub struct Completions<'a> {
+    pub predictor: &'a Predictor,
+    pub user_dictionary: &'a UserDictionary
+}
+
+impl<'a> WordPredictor for Completions<'a> {
+    fn words(&self, _previous: Option<&str>, prefix: &str) -> Result<Vec<Candidate>, PredictionError> {
+        if prefix.is_empty() {
+            return Ok(Vec::new());
+        }
+        self.predictor.word(prefix, self.user_dictionary)
+    }
+}
+
+//Words likely to follow the previous one, from the bigram model
+pub struct NextWords<'a>(pub &'a Predictor);
+
+impl<'a> WordPredictor for NextWords<'a> {
+    fn words(&self, previous: Option<&str>, prefix: &str) -> Result<Vec<Candidate>, PredictionError> {
+        match previous {
+            Some(previous) => self.0.next_word(previous, prefix),
+            None => Ok(Vec::new())
+        }
+    }
+}
+
+//Symbols by shortcode, keyword or name, with the ones used before first
+pub struct SymbolSearch<'a> {
+    pub predictor: &'a Predictor,
+    pub history: &'a SymbolHistory
+}
+
+impl<'a> SymbolPredictor for SymbolSearch<'a> {
+    fn symbols(&self, context: &str) -> Result<Vec<Candidate>, PredictionError> {
+        self.predictor.symbol(context, self.history)
+    }
+}
+
+
+
+//which data file each prediction file was loaded from and when that file was last modified
+type DataFingerprint = Vec<Option<(PathBuf, SystemTime)>>;
+
+fn data_files(config: &Config) -> Vec<String> {
+    config.dictionaries.iter()
+        .map(|dictionary| Dictionary::filename(dictionary.name.as_str()))
+        .chain(DATA_FILES.iter().map(|filename| String::from(*filename)))
+        .collect()
+}
+
+fn data_fingerprint(dirs: &[PathBuf], files: &[String]) -> DataFingerprint {
+    files.iter().map(|filename| {
+        find_data_file(dirs, filename).and_then(|path| {
+            fs::metadata(&path).and_then(|metadata| metadata.modified()).ok().map(|modified| (path, modified))
+        })
+    }).collect()
+}
+
+struct DataWatch {
+    fingerprint: DataFingerprint,
+    last_check: Instant
+}
+
+//Shared handle to the current predictor, which is swapped out whenever the data files change on disk.
+//Lookups hold their own Arc, so ones that are in flight during a swap finish on the old data.
+pub struct PredictorHandle {
+    dirs: Vec<PathBuf>,
+    config: Config,
+    files: Vec<String>,
+    check_interval: Duration,
+    current: Arc<RwLock<Arc<Predictor>>>,
+    watch: Mutex<DataWatch>,
+    //set while changed data is loaded in the background
+    reloading: Arc<AtomicBool>
 }
 
+impl PredictorHandle {
+    const CHECK_INTERVAL: Duration = Duration::from_secs(2);
 
+    fn new(dirs: Vec<PathBuf>, config: Config, check_interval: Duration) -> PredictorHandle {
+        let files = data_files(&config);
+        let fingerprint = data_fingerprint(&dirs, &files);
+        let predictor = Predictor::load_from(&dirs, &config).or_else(|err| {
+            log::error!("Failed to load prediction data, falling back to built in data: {}", err);
+            Predictor::embedded(&config)
+        }).unwrap_or_else(|err| {
+            //the built in data is checked by the tests, so this can only happen with a broken build
+            log::error!("Failed to load built in prediction data, there will be no predictions: {}", err);
+            Predictor::empty(&config)
+        });
+
+        PredictorHandle {
+            dirs,
+            config,
+            files,
+            check_interval,
+            current: Arc::new(RwLock::new(Arc::new(predictor))),
+            watch: Mutex::new(DataWatch {
+                fingerprint,
+                last_check: Instant::now()
+            }),
+            reloading: Arc::new(AtomicBool::new(false))
+        }
+    }
+
+    pub fn get(&self) -> Arc<Predictor> {
+        self.reload_if_changed();
+        self.current.read().unwrap_or_else(|poisoned| poisoned.into_inner()).clone()
+    }
+
+    fn reload_if_changed(&self) {
+        let mut watch = match self.watch.try_lock() {
+            Ok(watch) => watch,
+            Err(TryLockError::Poisoned(poisoned)) => poisoned.into_inner(),
+            //another lookup is already checking, so just use the current predictor
+            Err(TryLockError::WouldBlock) => return
+        };
+
+        //changes made during a reload are picked up by the next check after it
+        if watch.last_check.elapsed() < self.check_interval || self.reloading.load(Ordering::SeqCst) {
+            return;
+        }
+        watch.last_check = Instant::now();
+
+        let fingerprint = data_fingerprint(&self.dirs, &self.files);
+        if fingerprint == watch.fingerprint {
+            return;
+        }
+        watch.fingerprint = fingerprint;
+
+        //loading builds the prefix cache over every dictionary, which would hold up the key press that
+        //noticed the change, so lookups keep using the old data until the new data is ready
+        self.reloading.store(true, Ordering::SeqCst);
+        let dirs = self.dirs.clone();
+        let config = self.config.clone();
+        let current = Arc::clone(&self.current);
+        let reloading = Arc::clone(&self.reloading);
+        let spawned = thread::Builder::new().name(String::from("eei-reload")).spawn(move || {
+            match Predictor::load_from(&dirs, &config) {
+                Ok(predictor) => {
+                    log::info!("Prediction data changed on disk, reloaded");
+                    *current.write().unwrap_or_else(|poisoned| poisoned.into_inner()) = Arc::new(predictor);
+                }
+                Err(err) => {
+                    log::error!("Prediction data changed on disk but could not be reloaded, keeping the old data: {}", err);
+                }
+            }
+            reloading.store(false, Ordering::SeqCst);
+        });
+        if let Err(err) = spawned {
+            log::error!("Could not start reloading the prediction data, keeping the old data: {}", err);
+            self.reloading.store(false, Ordering::SeqCst);
+        }
+    }
+}
 
 lazy_static! {
-    pub static ref PREDICTOR: Predictor = Predictor {
-        dictionary: Map::new(include_bytes!("../../dictionary.fst").to_vec()).unwrap(),
-        shortcode_dictionary: Map::new(include_bytes!("../../shortcodes.fst").to_vec()).unwrap(),
-        symbols: bincode::deserialize(include_bytes!("../../symbols.bin")).unwrap()
-    };
+    static ref PREDICTOR: PredictorHandle = PredictorHandle::new(data_dirs(), config().clone(), PredictorHandle::CHECK_INTERVAL);
+}
+
+pub fn predictor() -> Arc<Predictor> {
+    PREDICTOR.get()
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::PREDICTOR;
+    use crate::candidate::{Candidate, CandidateSource};
+    use crate::config::{Config, DictionaryConfig};
+    use crate::data::Data;
+    use crate::emoji::SkinTone;
+    use crate::predict::{AutocorrectConfidence, Dictionary, Predictor, PredictionError, PredictorHandle};
+    use crate::symbol_history::SymbolHistory;
+    use crate::user_dictionary::UserDictionary;
+    use fst::Map;
+    use fst::automaton::{Automaton, Str};
+    use lazy_static::lazy_static;
+    use std::collections::HashMap;
+    use std::path::PathBuf;
+    use std::sync::atomic::Ordering;
+    use std::time::{Duration, Instant};
+
+    lazy_static! {
+        //the data compiled into the engine, so results don't depend on the config or data packs of the machine running the tests
+        static ref EMBEDDED_PREDICTOR: Predictor = Predictor::embedded(&Config::default()).unwrap();
+    }
+
+    fn predictor() -> &'static Predictor {
+        &EMBEDDED_PREDICTOR
+    }
+
+    //what picking each candidate would type
+    fn commits(candidates: Vec<Candidate>) -> Vec<String> {
+        candidates.into_iter().map(|candidate| candidate.commit).collect()
+    }
+
+    //(shown, typed) pairs, as in the symbol table
+    fn labels(candidates: Vec<Candidate>) -> Vec<(String, String)> {
+        candidates.into_iter().map(|candidate| (candidate.display, candidate.commit)).collect()
+    }
+
+    fn fst_bytes<K: AsRef<[u8]>>(entries: Vec<(K, u64)>) -> Vec<u8> {
+        Map::from_iter(entries).unwrap().into_fst().into_inner()
+    }
+
+    fn map(entries: Vec<(&str, u64)>) -> Map<Data> {
+        Map::new(Data::Owned(fst_bytes(entries))).unwrap()
+    }
+
+    //a predictor with just these dictionary words and bigrams, and no symbols
+    fn test_predictor(words: Vec<(&str, u64)>, bigrams: Vec<(&str, u64)>) -> Predictor {
+        let dictionaries = if words.is_empty() {
+            Vec::new()
+        } else {
+            vec![Dictionary { name: String::from("test"), words: map(words), weight: 1.0 }]
+        };
+        Predictor {
+            dictionaries,
+            prefix_cache: HashMap::new(),
+            shortcode_dictionary: map(vec![]),
+            symbols: Vec::new(),
+            symbol_names: Vec::new(),
+            keywords: map(vec![]),
+            character_names: map(vec![]),
+            bigrams: map(bigrams),
+            max_edit_distance: 0,
+            skin_tone: None
+        }
+    }
+
+    //a data directory of its own for each test, removed when the test is done with it
+    struct TempDir(PathBuf);
+
+    impl TempDir {
+        fn new(name: &str) -> TempDir {
+            let dir = std::env::temp_dir().join(format!("eei_{}_{}", name, std::process::id()));
+            std::fs::create_dir_all(&dir).unwrap();
+            TempDir(dir)
+        }
+
+        fn write<C: AsRef<[u8]>>(&self, filename: &str, contents: C) {
+            std::fs::write(self.0.join(filename), contents).unwrap();
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.0);
+        }
+    }
 
     fn symbol_test(head: &str) {
-        let symbol_results = PREDICTOR.symbol(head).unwrap();
+        let symbol_results = labels(predictor().symbol(head, &SymbolHistory::in_memory()).unwrap());
         println!("symbols for {head}", head=head);
         for (shortcode, symbol) in symbol_results {
             println!("{shortcode} : {symbol}", shortcode=shortcode, symbol=symbol);
@@ -105,7 +1141,7 @@ mod tests {
     }
 
     fn word_test(head: &str) {
-        let word_results = PREDICTOR.word(head).unwrap();
+        let word_results = commits(predictor().word(head, &UserDictionary::in_memory()).unwrap());
 
         println!("words for {head}:", head=head);
         for word in word_results {
@@ -122,4 +1158,427 @@ mod tests {
         word_test("Lit");
         word_test("LiT");
     }
+
+    #[test]
+    fn fuzzy_words() {
+        let user_dictionary = UserDictionary::in_memory();
+        let results = commits(predictor().word("definat", &user_dictionary).unwrap());
+        assert!(results.contains(&String::from("definitely")));
+        assert!(commits(predictor().word("recie", &user_dictionary).unwrap()).contains(&String::from("receive")));
+        assert!(commits(predictor().word_with_distance("recie", &user_dictionary, 0).unwrap()).is_empty());
+
+        //exact prefix matches come before any typo matches
+        let results = commits(predictor().word("quak", &user_dictionary).unwrap());
+        assert_eq!(results.first().map(String::as_str), Some("quake"));
+        let first_fuzzy = results.iter().position(|word| !word.starts_with("quak")).unwrap();
+        assert!(results[first_fuzzy..].iter().all(|word| !word.starts_with("quak")));
+    }
+
+    #[test]
+    fn corrections() {
+        let corrections = commits(predictor().corrections("teh").unwrap());
+        assert_eq!(corrections.first().map(String::as_str), Some("the"));
+        assert!(corrections.contains(&String::from("ten")));
+        assert!(!corrections.contains(&String::from("then")));
+        assert_eq!(commits(predictor().corrections("Recieve").unwrap()).first().map(String::as_str), Some("Receive"));
+
+        //closer words come first, however rare
+        let corrections = commits(predictor().corrections("definately").unwrap());
+        let distances = corrections.iter().map(|word| Predictor::edit_distances("definately", word).1).collect::<Vec<_>>();
+        assert!(corrections.contains(&String::from("definitely")));
+        assert!(distances.windows(2).all(|pair| pair[0] <= pair[1]));
+
+        let config = Config { max_edit_distance: 0, ..Config::default() };
+        assert!(Predictor::embedded(&config).unwrap().corrections("teh").unwrap().is_empty());
+    }
+
+    #[test]
+    fn autocorrection() {
+        let confidence = AutocorrectConfidence::default();
+        let autocorrect = |word: &str, confidence: &AutocorrectConfidence| {
+            predictor().autocorrection(word, confidence).unwrap().map(|candidate| candidate.commit)
+        };
+
+        assert_eq!(autocorrect("teh", &confidence).as_deref(), Some("the"));
+        assert_eq!(autocorrect("Thier", &confidence).as_deref(), Some("Their"));
+        assert_eq!(autocorrect("recieve", &confidence).as_deref(), Some("receive"));
+        assert_eq!(autocorrect("definately", &confidence).as_deref(), Some("definitely"));
+        //"last", "test" and "east" are all likely
+        assert_eq!(autocorrect("tast", &confidence), None);
+        //"weird" and "wired" are both swaps
+        assert_eq!(autocorrect("wierd", &confidence), None);
+        //"crustacean" is too rare to be sure of
+        assert_eq!(autocorrect("rustacean", &confidence), None);
+
+        //"what" only beats "want" by enough as a swap
+        assert_eq!(autocorrect("waht", &confidence).as_deref(), Some("what"));
+        let no_swaps = AutocorrectConfidence { transposition_weight: 1.0, ..AutocorrectConfidence::default() };
+        assert_eq!(autocorrect("waht", &no_swaps), None);
+        let strict = AutocorrectConfidence { min_lead: 1000.0, ..AutocorrectConfidence::default() };
+        assert_eq!(autocorrect("teh", &strict), None);
+        let unsure = AutocorrectConfidence { max_distance: 0, ..AutocorrectConfidence::default() };
+        assert_eq!(autocorrect("teh", &unsure), None);
+    }
+
+    #[test]
+    fn user_words() {
+        let mut user_dictionary = UserDictionary::in_memory();
+        assert!(!commits(predictor().word("rustac", &user_dictionary).unwrap()).contains(&String::from("rustacean")));
+
+        user_dictionary.record("rustacean");
+        let learned = predictor().word("rustac", &user_dictionary).unwrap().remove(0);
+        assert_eq!(learned.commit, "rustacean");
+        assert_eq!(learned.source, CandidateSource::User);
+
+        //picking a dictionary word repeatedly moves it ahead of more frequent words
+        let before = commits(predictor().word("lit", &user_dictionary).unwrap());
+        let rare_word = before.last().unwrap().clone();
+        for _ in 0..100 {
+            user_dictionary.record(rare_word.as_str());
+        }
+        assert_eq!(commits(predictor().word("lit", &user_dictionary).unwrap()).first(), Some(&rare_word));
+    }
+
+    #[test]
+    fn contractions_and_compounds() {
+        let mut user_dictionary = UserDictionary::in_memory();
+        assert_eq!(commits(predictor().word("doesn", &user_dictionary).unwrap()).first().map(String::as_str), Some("doesn't"));
+        assert!(commits(predictor().word("they'", &user_dictionary).unwrap()).contains(&String::from("they're")));
+
+        let compounds = commits(predictor().word("well-kno", &user_dictionary).unwrap());
+        assert_eq!(compounds.first().map(String::as_str), Some("well-know"));
+        assert!(compounds.contains(&String::from("well-known")));
+        assert!(compounds.iter().all(|word| word.starts_with("well-")));
+        assert!(predictor().word("well-", &user_dictionary).unwrap().is_empty());
+
+        //a learned compound comes before ones made up from its parts
+        user_dictionary.record("well-known");
+        assert_eq!(commits(predictor().word("well-kno", &user_dictionary).unwrap()).first().map(String::as_str), Some("well-known"));
+    }
+
+    #[test]
+    fn next_words() {
+        let predictor = test_predictor(vec![], vec![("of course", 5), ("of the", 40), ("of them", 7), ("off the", 9)]);
+
+        assert_eq!(commits(predictor.next_word("Of", "").unwrap()), vec!["the", "them", "course"]);
+        assert_eq!(commits(predictor.next_word("of", "th").unwrap()), vec!["the", "them"]);
+        assert_eq!(commits(predictor.next_word("of", "Th").unwrap()), vec!["The", "Them"]);
+        assert!(commits(predictor.next_word("the", "").unwrap()).is_empty());
+
+        //no bigrams are shipped, so there is nothing to suggest until a corpus is supplied
+        assert!(commits(EMBEDDED_PREDICTOR.next_word("of", "").unwrap()).is_empty());
+    }
+
+    #[test]
+    fn accented_words() {
+        let predictor = test_predictor(vec![("cafe", 5), ("café", 10), ("naïve", 3), ("zoë", 1), ("éclair", 2)], vec![]);
+        let user_dictionary = UserDictionary::in_memory();
+
+        assert_eq!(commits(predictor.word("caf", &user_dictionary).unwrap()), vec!["café", "cafe"]);
+        assert_eq!(commits(predictor.word("naï", &user_dictionary).unwrap()), vec!["naïve"]);
+        assert_eq!(commits(predictor.word("Zo", &user_dictionary).unwrap()), vec!["Zoë"]);
+        assert_eq!(commits(predictor.word("Écl", &user_dictionary).unwrap()), vec!["Éclair"]);
+        assert!(Predictor::is_title_cased("Écl"));
+        assert!(!Predictor::is_title_cased("ÉCl"));
+        assert_eq!(Predictor::fold_case("ÉCLAIR"), "éclair");
+
+        //the shipped dictionary has them too, as often used as their unaccented spelling
+        let words = |context: &str| commits(EMBEDDED_PREDICTOR.word(context, &user_dictionary).unwrap());
+        assert!(words("caf").contains(&String::from("café")));
+        assert!(words("naï").contains(&String::from("naïve")));
+        assert!(words("jalape").contains(&String::from("jalapeño")));
+    }
+
+    #[test]
+    fn capitalisation() {
+        let capitalised = |mask: u64, freq: u64| mask << Dictionary::CASE_SHIFT | freq;
+        let predictor = test_predictor(vec![
+            ("iphone", capitalised(0b10, 3)),
+            ("london", capitalised(0b1, 5)),
+            ("nasa", capitalised(0b1111, 4)),
+            ("nasal", 2)
+        ], vec![("in london", 3), ("in the", 1)]);
+        let user_dictionary = UserDictionary::in_memory();
+        let words = |context: &str| commits(predictor.word(context, &user_dictionary).unwrap());
+
+        assert_eq!(words("nas"), vec!["NASA", "nasal"]);
+        assert_eq!(words("Nas"), vec!["NASA", "Nasal"]);
+        assert_eq!(words("NAS"), vec!["NASA", "NASAL"]);
+        assert_eq!(words("lon"), vec!["London"]);
+        assert_eq!(words("Iph"), vec!["iPhone"]);
+        assert_eq!(words("IPH"), vec!["IPHONE"]);
+        assert_eq!(commits(predictor.next_word("in", "").unwrap()), vec!["London", "the"]);
+        //the capitals don't count towards the frequency
+        assert_eq!(predictor.frequency("london"), 5);
+
+        //the shipped dictionary keeps the capitals of names, but not of words that are also lowercase
+        let shipped = |context: &str| commits(EMBEDDED_PREDICTOR.word(context, &user_dictionary).unwrap());
+        assert!(shipped("lon").contains(&String::from("London")));
+        assert!(!shipped("lon").contains(&String::from("london")));
+        assert!(shipped("nas").contains(&String::from("NASA")));
+        assert!(shipped("iph").contains(&String::from("iPhone")));
+        assert!(shipped("ma").contains(&String::from("may")));
+    }
+
+    #[test]
+    fn top_words() {
+        let mut config = Config::default();
+        config.dictionaries[0].weight = 0.5;
+        let mut predictor = Predictor::embedded(&config).unwrap();
+        predictor.dictionaries.push(Dictionary {
+            name: String::from("short"),
+            words: map(vec![("tax", 1), ("the", 1), ("thezzz", 1 << 40)]),
+            weight: 1.0
+        });
+        predictor.prefix_cache = Predictor::build_prefix_cache(&predictor.dictionaries).unwrap();
+        assert!(predictor.prefix_cache.contains_key("th"));
+        assert!(!predictor.prefix_cache.contains_key("thezz"));
+
+        //cached and streamed results match a plain sort of every match
+        for prefix in ["", "t", "th", "the", "thez", "zyzzyva"].iter() {
+            let mut expected = predictor.search_dictionaries(Str::new(prefix).starts_with()).unwrap();
+            expected.sort_by(|(w1, f1), (w2, f2)| f2.cmp(f1).then(w1.cmp(w2)));
+            expected.truncate(Predictor::WORD_COUNT);
+            assert_eq!(predictor.top_dictionary_words(prefix).unwrap(), expected);
+        }
+        assert_eq!(predictor.top_dictionary_words("th").unwrap()[0].0, "thezzz");
+    }
+
+    #[test]
+    fn load_data_pack() {
+        let dir = TempDir::new("data_pack");
+        dir.write("dictionary.fst", fst_bytes(vec![("zyzzyva", 1)]));
+
+        //files missing from the pack come from the built in data
+        let predictor = Predictor::load_from(std::slice::from_ref(&dir.0), &Config::default()).unwrap();
+        let user_dictionary = UserDictionary::in_memory();
+        assert_eq!(commits(predictor.word("zyz", &user_dictionary).unwrap()), vec!["zyzzyva"]);
+        assert!(commits(predictor.word("lit", &user_dictionary).unwrap()).is_empty());
+        assert!(!labels(predictor.symbol("smile", &SymbolHistory::in_memory()).unwrap()).is_empty());
+
+        dir.write("symbols.bin", b"not bincode");
+        let result = Predictor::load_from(std::slice::from_ref(&dir.0), &Config::default());
+        assert!(matches!(result, Err(PredictionError::DecodeError(_))));
+        assert!(Predictor::embedded(&Config::default()).is_ok());
+    }
+
+    #[test]
+    fn hot_reload() {
+        let dir = TempDir::new("hot_reload");
+        let handle = PredictorHandle::new(vec![dir.0.clone()], Config::default(), Duration::from_secs(0));
+        let user_dictionary = UserDictionary::in_memory();
+
+        let old_predictor = handle.get();
+        assert!(commits(old_predictor.word("zyz", &user_dictionary).unwrap()).is_empty());
+
+        dir.write("dictionary.fst.tmp", fst_bytes(vec![("zyzzyva", 1)]));
+        std::fs::rename(dir.0.join("dictionary.fst.tmp"), dir.0.join("dictionary.fst")).unwrap();
+
+        //the lookup that notices the change still gets the old data, since the new data is loaded in the background
+        handle.get();
+        wait_for_reload(&handle);
+        assert_eq!(commits(handle.get().word("zyz", &user_dictionary).unwrap()), vec!["zyzzyva"]);
+        //lookups that started before the swap keep their data
+        assert!(!commits(old_predictor.word("lit", &user_dictionary).unwrap()).is_empty());
+
+        //a broken file keeps the last good data around
+        dir.write("symbols.bin", b"not bincode");
+        handle.get();
+        wait_for_reload(&handle);
+        assert_eq!(commits(handle.get().word("zyz", &user_dictionary).unwrap()), vec!["zyzzyva"]);
+    }
+
+    fn wait_for_reload(handle: &PredictorHandle) {
+        let start = Instant::now();
+        while handle.reloading.load(Ordering::SeqCst) {
+            assert!(start.elapsed() < Duration::from_secs(60), "reload did not finish");
+            std::thread::sleep(Duration::from_millis(10));
+        }
+    }
+
+    #[test]
+    fn empty_predictor() {
+        let empty = Predictor::empty(&Config::default());
+        assert!(empty.word("the", &UserDictionary::in_memory()).unwrap().is_empty());
+        assert!(empty.symbol("heart", &SymbolHistory::in_memory()).unwrap().is_empty());
+    }
+
+    #[test]
+    fn multiple_dictionaries() {
+        let dir = TempDir::new("dictionaries");
+        dir.write("dictionary_jargon.fst", fst_bytes(vec![("literate", 1), ("litestream", 10)]));
+
+        let mut config = Config::default();
+        config.dictionaries.push(DictionaryConfig { name: String::from("jargon"), weight: 1e9 });
+        config.dictionaries.push(DictionaryConfig { name: String::from("missing"), weight: 1.0 });
+        let predictor = Predictor::load_from(std::slice::from_ref(&dir.0), &config).unwrap();
+
+        //words from both dictionaries are merged, and a word in both keeps its best weighted frequency
+        let user_dictionary = UserDictionary::in_memory();
+        let results = commits(predictor.word("lite", &user_dictionary).unwrap());
+        assert_eq!(&results[..2], ["litestream", "literate"]);
+        assert!(results.contains(&String::from("literal")));
+        assert_eq!(results.iter().filter(|word| *word == "literate").count(), 1);
+        assert!(predictor.contains("litestream"));
+
+        config.dictionaries.remove(0);
+        let predictor = Predictor::embedded(&config).unwrap();
+        assert!(predictor.contains("literal"));
+    }
+
+    #[test]
+    fn symbol_segments() {
+        let shortcodes = |context: &str| -> Vec<String> {
+            labels(predictor().symbol(context, &SymbolHistory::in_memory()).unwrap()).into_iter().map(|(shortcode, _symbol)| shortcode).collect()
+        };
+
+        let arrows = shortcodes("arrow");
+        assert!(arrows.contains(&String::from("left_right_arrow")));
+        let first_infix = arrows.iter().position(|shortcode| !shortcode.starts_with("arrow")).unwrap();
+        assert!(first_infix > 0);
+        assert!(arrows[first_infix..].iter().all(|shortcode| !shortcode.starts_with("arrow")));
+
+        assert!(shortcodes("heart").contains(&String::from("broken_heart")));
+        assert!(shortcodes("right_arr").contains(&String::from("left_right_arrow")));
+    }
+
+    #[test]
+    fn symbol_names() {
+        let symbols = |context: &str| -> Vec<(String, String)> {
+            labels(predictor().symbol(context, &SymbolHistory::in_memory()).unwrap())
+        };
+
+        let alpha = (String::from("greek small letter alpha"), String::from("α"));
+        assert!(symbols("GREEK SMALL LETTER ALPHA").contains(&alpha));
+        assert!(symbols("greek alpha").contains(&alpha));
+        //space closes the symbol table, so words of a name can also be separated with _
+        assert!(symbols("greek_alpha").contains(&alpha));
+        assert!(symbols("gre sma alph").contains(&alpha));
+        assert!(!symbols("greek alpha").iter().any(|(_name, symbol)| symbol == "β"));
+
+        //symbols that a shortcode already found aren't repeated under their name
+        let rockets = symbols("rocket");
+        assert_eq!(rockets.iter().filter(|(_shortcode, symbol)| symbol == "🚀").count(), 1);
+        assert_eq!(rockets.first().map(|(shortcode, _symbol)| shortcode.as_str()), Some("rocket"));
+
+        let alpha = predictor().symbol("greek alpha", &SymbolHistory::in_memory()).unwrap().into_iter()
+            .find(|candidate| candidate.commit == "α")
+            .unwrap();
+        assert_eq!(alpha.source, CandidateSource::Math);
+        //the name is already the label, so there's nothing more to describe
+        assert_eq!(alpha.description, None);
+        let rocket = predictor().symbol("rocket", &SymbolHistory::in_memory()).unwrap().remove(0);
+        assert_eq!(rocket.source, CandidateSource::Emoji);
+
+        assert_eq!(predictor().character_name('α').as_deref(), Some("greek small letter alpha"));
+        //characters outside the symbol table have names too
+        assert_eq!(predictor().character_name('é').as_deref(), Some("latin small letter e with acute"));
+        assert_eq!(predictor().character_name('\u{1F600}').as_deref(), Some("grinning face"));
+        //unassigned
+        assert_eq!(predictor().character_name('\u{378}'), None);
+    }
+
+    #[test]
+    fn latex_commands() {
+        let mut latex = test_predictor(vec![], vec![]);
+        latex.shortcode_dictionary = map(vec![
+            ("\\alpha", 0),
+            ("\\forall", 1),
+            ("\\mathbb{R}", 2),
+            ("\\rightarrow", 3),
+            ("\\to", 3),
+            ("alpha", 0),
+            ("arrow_right", 3)
+        ]);
+        latex.symbols = vec![String::from("α"), String::from("∀"), String::from("ℝ"), String::from("→")];
+        latex.symbol_names = vec![String::from("greek small letter alpha"), String::from("for all"),
+                                      String::from("double-struck capital r"), String::from("rightwards arrow")];
+        let history = SymbolHistory::in_memory();
+        let symbols = |context: &str| labels(latex.symbol(context, &history).unwrap());
+
+        assert_eq!(symbols("\\alp"), vec![(String::from("\\alpha"), String::from("α"))]);
+        assert_eq!(symbols("\\mathbb{R"), vec![(String::from("\\mathbb{R}"), String::from("ℝ"))]);
+        assert_eq!(symbols("\\forall"), vec![(String::from("\\forall"), String::from("∀"))]);
+        assert!(symbols("\\r").contains(&(String::from("\\rightarrow"), String::from("→"))));
+        //names and keywords aren't searched for LaTeX commands
+        assert!(symbols("\\greek").is_empty());
+
+        //and LaTeX commands don't show up in other searches
+        assert!(symbols("alpha").iter().all(|(label, _symbol)| !label.starts_with('\\')));
+        assert_eq!(symbols("right").first(), Some(&(String::from("arrow_right"), String::from("→"))));
+        assert!(symbols("right").iter().all(|(label, _symbol)| !label.starts_with('\\')));
+
+        let alpha = latex.symbol("\\alpha", &history).unwrap().remove(0);
+        assert_eq!(alpha.source, CandidateSource::Math);
+
+        //the shipped data has the commands too
+        let embedded = labels(predictor().symbol("\\alpha", &history).unwrap());
+        assert_eq!(embedded.first(), Some(&(String::from("\\alpha"), String::from("α"))));
+        let embedded = labels(predictor().symbol("\\rightarrow", &history).unwrap());
+        assert_eq!(embedded.first(), Some(&(String::from("\\rightarrow"), String::from("→"))));
+    }
+
+    #[test]
+    fn symbol_variants() {
+        let variants = labels(predictor().symbol_variants("biking_man", "🚴\u{200D}♂\u{FE0F}"));
+        let shortcodes = variants.iter().map(|(shortcode, _symbol)| shortcode.as_str()).collect::<Vec<_>>();
+        assert_eq!(shortcodes.len(), 12);
+        assert_eq!(&shortcodes[..2], ["biking_man", "biking_man light"]);
+        assert!(shortcodes.contains(&"biking_woman dark"));
+        assert_eq!(labels(predictor().symbol_variants("rocket", "🚀")).len(), 1);
+    }
+
+    #[test]
+    fn qualified_emoji() {
+        let first = |shortcode: &str| labels(predictor().symbol(shortcode, &SymbolHistory::in_memory()).unwrap()).into_iter().next();
+        assert_eq!(first("heart"), Some((String::from("heart"), String::from("\u{2764}\u{FE0F}"))));
+        assert_eq!(first("family_man_woman_girl"),
+                   Some((String::from("family_man_woman_girl"), String::from("👨\u{200D}👩\u{200D}👧"))));
+        //emoji GitHub doesn't have get a shortcode from their name
+        assert_eq!(first("face_with_spiral_eyes"), Some((String::from("face_with_spiral_eyes"), String::from("😵\u{200D}💫"))));
+    }
+
+    #[test]
+    fn symbol_keywords() {
+        let ident = |symbol: &str| predictor().symbols.iter().position(|s| s == symbol).unwrap() as u64;
+        let mut keywords = vec![
+            (format!("laugh\x1f{}", "😂"), ident("😂")),
+            (format!("tears_of_joy\x1f{}", "😂"), ident("😂")),
+            (format!("laugh\x1f{}", "😆"), ident("😆")),
+            (format!("rocket\x1f{}", "🚀"), ident("🚀"))
+        ];
+        keywords.sort();
+        let dir = TempDir::new("keywords");
+        dir.write("keywords.fst", fst_bytes(keywords));
+        let annotated = Predictor::load_from(std::slice::from_ref(&dir.0), &Config::default()).unwrap();
+
+        let history = SymbolHistory::in_memory();
+        let laughs = labels(annotated.symbol("laugh", &history).unwrap());
+        //the shortcode hit comes first and isn't repeated for its keyword
+        assert_eq!(laughs.first(), Some(&(String::from("laughing"), String::from("😆"))));
+        assert!(laughs.contains(&(String::from("laugh"), String::from("😂"))));
+        assert_eq!(laughs.iter().filter(|(_label, symbol)| symbol == "😆").count(), 1);
+        assert!(labels(annotated.symbol("joy", &history).unwrap()).contains(&(String::from("joy"), String::from("😂"))));
+        assert!(labels(annotated.symbol("of jo", &history).unwrap()).contains(&(String::from("tears of joy"), String::from("😂"))));
+        assert!(labels(predictor().symbol("laugh", &history).unwrap()).iter().all(|(_label, symbol)| symbol != "😂"));
+        //no keywords are shipped, so only shortcodes and names are searched out of the box
+        assert!(predictor().keywords.is_empty());
+    }
+
+    #[test]
+    fn symbol_history_boost() {
+        let mut history = SymbolHistory::in_memory();
+        let arrows = labels(predictor().symbol("arrow", &history).unwrap());
+        let (shortcode, symbol) = arrows.last().unwrap().clone();
+        history.record(shortcode.as_str(), symbol.as_str());
+
+        let boosted = labels(predictor().symbol("arrow", &history).unwrap());
+        assert_eq!(boosted.first(), Some(&(shortcode, symbol)));
+        assert_eq!(&boosted[1..], &arrows[..arrows.len() - 1]);
+
+        let config = Config { skin_tone: Some(SkinTone::Dark), ..Config::default() };
+        let predictor = Predictor::embedded(&config).unwrap();
+        let thumbs = labels(predictor.symbol("+1", &SymbolHistory::in_memory()).unwrap());
+        assert_eq!(thumbs.first().map(|(_shortcode, symbol)| symbol.as_str()), Some("👍🏿"));
+    }
 }
@@ -0,0 +1,79 @@
+use std::fmt;
+
+//Text typed as superscript or subscript, as in x² or H₂O
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Script {
+    Superscript,
+    Subscript
+}
+
+//Unicode only has superscript and subscript forms for some letters, e.g. no superscript q or subscript b
+const SUPERSCRIPT_FROM: &str = "0123456789+-=()abcdefghijklmnoprstuvwxyzABDEGHIJKLMNOPRTUVW";
+const SUPERSCRIPT_TO: &str = "⁰¹²³⁴⁵⁶⁷⁸⁹⁺⁻⁼⁽⁾ᵃᵇᶜᵈᵉᶠᵍʰⁱʲᵏˡᵐⁿᵒᵖʳˢᵗᵘᵛʷˣʸᶻᴬᴮᴰᴱᴳᴴᴵᴶᴷᴸᴹᴺᴼᴾᴿᵀᵁⱽᵂ";
+const SUBSCRIPT_FROM: &str = "0123456789+-=()aehijklmnoprstuvx";
+const SUBSCRIPT_TO: &str = "₀₁₂₃₄₅₆₇₈₉₊₋₌₍₎ₐₑₕᵢⱼₖₗₘₙₒₚᵣₛₜᵤᵥₓ";
+
+impl Script {
+    //"^" or "_" typed in the symbol table switches to superscript or subscript, as in LaTeX
+    pub fn from_prefix(input: &str) -> Option<Script> {
+        match input {
+            "^" => Some(Script::Superscript),
+            "_" => Some(Script::Subscript),
+            _ => None
+        }
+    }
+
+    pub fn convert(&self, c: char) -> Option<char> {
+        let (from, to) = match self {
+            Script::Superscript => (SUPERSCRIPT_FROM, SUPERSCRIPT_TO),
+            Script::Subscript => (SUBSCRIPT_FROM, SUBSCRIPT_TO)
+        };
+        from.chars().position(|plain| plain == c).and_then(|idx| to.chars().nth(idx))
+    }
+
+    //what the auxiliary text shows, including the last character that couldn't be converted
+    pub fn preview(&self, rejected: Option<char>) -> String {
+        match rejected {
+            Some(c) => format!("{}: {} has no {} form", self, c, self),
+            None => self.to_string()
+        }
+    }
+}
+
+impl fmt::Display for Script {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Script::Superscript => write!(f, "superscript"),
+            Script::Subscript => write!(f, "subscript"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::script::{Script, SUBSCRIPT_FROM, SUBSCRIPT_TO, SUPERSCRIPT_FROM, SUPERSCRIPT_TO};
+
+    #[test]
+    fn convert() {
+        assert_eq!(SUPERSCRIPT_FROM.chars().count(), SUPERSCRIPT_TO.chars().count());
+        assert_eq!(SUBSCRIPT_FROM.chars().count(), SUBSCRIPT_TO.chars().count());
+
+        let superscript = |text: &str| text.chars().map(|c| Script::Superscript.convert(c)).collect::<Option<String>>();
+        let subscript = |text: &str| text.chars().map(|c| Script::Subscript.convert(c)).collect::<Option<String>>();
+        assert_eq!(superscript("2"), Some(String::from("²")));
+        assert_eq!(superscript("n+1"), Some(String::from("ⁿ⁺¹")));
+        assert_eq!(superscript("-(x)"), Some(String::from("⁻⁽ˣ⁾")));
+        assert_eq!(superscript("TM"), Some(String::from("ᵀᴹ")));
+        assert_eq!(superscript("q"), None);
+        assert_eq!(subscript("2"), Some(String::from("₂")));
+        assert_eq!(subscript("i-1"), Some(String::from("ᵢ₋₁")));
+        assert_eq!(subscript("b"), None);
+
+        assert_eq!(Script::from_prefix("^"), Some(Script::Superscript));
+        assert_eq!(Script::from_prefix("_"), Some(Script::Subscript));
+        assert_eq!(Script::from_prefix("^2"), None);
+
+        assert_eq!(Script::Superscript.preview(None), "superscript");
+        assert_eq!(Script::Subscript.preview(Some('b')), "subscript: b has no subscript form");
+    }
+}
@@ -0,0 +1,142 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+use chrono::{DateTime, Local};
+use lazy_static::lazy_static;
+use crate::backend::SymbolPredictor;
+use crate::candidate::{Candidate, CandidateSource};
+use crate::predict::PredictionError;
+use crate::snippets::SnippetError::*;
+use crate::user_data_dir;
+
+//Abbreviations the user expands to longer text, read from a TOML file of `abbreviation = "text"` lines.
+//The text can span several lines and contain {date}, {time} and {weekday} placeholders.
+#[derive(Debug, Default, PartialEq)]
+pub struct Snippets {
+    snippets: BTreeMap<String, String>
+}
+
+#[derive(Debug)]
+pub enum SnippetError {
+    IoError(io::Error),
+    ParseError(toml::de::Error)
+}
+
+impl fmt::Display for SnippetError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            IoError(err) => write!(f, "Failed to read snippets: {}", err),
+            ParseError(err) => write!(f, "Invalid snippets: {}", err),
+        }
+    }
+}
+
+impl Snippets {
+    const FILENAME: &'static str = "snippets.toml";
+    //longest part of the expansion shown in the table
+    const PREVIEW_LEN: usize = 30;
+
+    pub fn parse(text: &str) -> Result<Snippets, SnippetError> {
+        toml::from_str(text).map(|snippets| Snippets { snippets }).map_err(ParseError)
+    }
+
+    pub fn load(path: &Path) -> Result<Snippets, SnippetError> {
+        match fs::read_to_string(path) {
+            Ok(text) => Snippets::parse(text.as_str()),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(Snippets::default()),
+            Err(err) => Err(IoError(err))
+        }
+    }
+
+    fn expand(text: &str, now: &DateTime<Local>) -> String {
+        text.replace("{date}", now.format("%Y-%m-%d").to_string().as_str())
+            .replace("{time}", now.format("%H:%M").to_string().as_str())
+            .replace("{weekday}", now.format("%A").to_string().as_str())
+    }
+
+    //the first line of the expansion, shortened to fit in the table
+    pub fn preview(expansion: &str) -> String {
+        let first_line = expansion.lines().next().unwrap_or("");
+        if first_line.chars().count() > Snippets::PREVIEW_LEN || first_line.len() < expansion.trim_end().len() {
+            first_line.chars().take(Snippets::PREVIEW_LEN).collect::<String>() + "…"
+        } else {
+            String::from(first_line)
+        }
+    }
+
+    //snippets with abbreviations starting with the context, with their placeholders filled in
+    pub fn matches(&self, context: &str, now: &DateTime<Local>) -> Vec<Candidate> {
+        self.snippets.range(String::from(context)..)
+            .take_while(|(abbreviation, _text)| abbreviation.starts_with(context))
+            .map(|(abbreviation, text)| Candidate {
+                display: abbreviation.clone(),
+                commit: Snippets::expand(text.as_str(), now),
+                score: 0,
+                source: CandidateSource::Snippet,
+                description: None
+            })
+            .collect()
+    }
+}
+
+lazy_static! {
+    static ref SNIPPETS: Snippets = match user_data_dir().map(|dir| Snippets::load(dir.join(Snippets::FILENAME).as_path())) {
+        Ok(Ok(snippets)) => snippets,
+        Ok(Err(err)) => {
+            log::error!("{}, no snippets will be offered", err);
+            Snippets::default()
+        }
+        Err(err) => {
+            log::error!("Could not find user data directory, no snippets will be offered: {}", err);
+            Snippets::default()
+        }
+    };
+}
+
+pub fn snippets() -> &'static Snippets {
+    &SNIPPETS
+}
+
+//Snippets offered in the symbol table, expanded at the time of the search
+pub struct SnippetSearch<'a>(pub &'a Snippets);
+
+impl<'a> SymbolPredictor for SnippetSearch<'a> {
+    fn symbols(&self, context: &str) -> Result<Vec<Candidate>, PredictionError> {
+        Ok(self.0.matches(context, &Local::now()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Local, TimeZone};
+    use crate::snippets::Snippets;
+
+    #[test]
+    fn parse_and_expand() {
+        let snippets = Snippets::parse(r#"
+            sig = """
+Best regards,
+Alex
+"""
+            addr = "1 Main Street"
+            today = "Today is {weekday} {date}, {time}"
+        "#).unwrap();
+        let now = Local.with_ymd_and_hms(2024, 3, 5, 9, 7, 0).unwrap();
+
+        let expansions = |context: &str| snippets.matches(context, &now).into_iter()
+            .map(|candidate| (candidate.display, candidate.commit))
+            .collect::<Vec<_>>();
+        assert_eq!(expansions("sig"), vec![(String::from("sig"), String::from("Best regards,\nAlex\n"))]);
+        assert_eq!(expansions("to"), vec![(String::from("today"), String::from("Today is Tuesday 2024-03-05, 09:07"))]);
+        assert_eq!(expansions("").len(), 3);
+        assert!(expansions("x").is_empty());
+
+        assert_eq!(Snippets::preview("1 Main Street"), "1 Main Street");
+        assert_eq!(Snippets::preview("Best regards,\nAlex\n"), "Best regards,…");
+
+        assert!(Snippets::parse("sig = 1").is_err());
+        assert_eq!(Snippets::load(std::path::Path::new("/nonexistent/snippets.toml")).unwrap(), Snippets::default());
+    }
+}
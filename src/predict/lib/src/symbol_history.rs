@@ -0,0 +1,152 @@
+use std::cmp::{min, Reverse};
+use std::collections::HashSet;
+use std::io;
+use std::path::PathBuf;
+use std::sync::{Mutex, MutexGuard};
+use lazy_static::lazy_static;
+use crate::persistent::{lock, PersistentTable, Record};
+
+#[derive(Debug, Clone, PartialEq)]
+struct SymbolUsage {
+    shortcode: String,
+    count: u64,
+    //position in the overall order of commits, higher is more recent
+    last_used: u64
+}
+
+impl Record for SymbolUsage {
+    fn parse(fields: &[&str]) -> Option<SymbolUsage> {
+        match fields {
+            [shortcode, count, last_used, ..] if !shortcode.is_empty() => Some(SymbolUsage {
+                shortcode: String::from(*shortcode),
+                count: count.parse().ok()?,
+                last_used: last_used.parse().ok()?
+            }),
+            _ => None
+        }
+    }
+
+    fn fields(&self) -> Vec<String> {
+        vec![self.shortcode.clone(), self.count.to_string(), self.last_used.to_string()]
+    }
+}
+
+//Symbols the user has committed from the symbol table, persisted as "symbol\tshortcode\tcount\tlast_used" lines
+pub struct SymbolHistory {
+    usage: PersistentTable<SymbolUsage>
+}
+
+impl SymbolHistory {
+    const FILENAME: &'static str = "symbol_history.txt";
+    //how many of the suggestions shown for an empty search are the most recently used symbols,
+    //the rest are the most frequently used
+    const RECENT_COUNT: usize = 5;
+
+    //a history that is never written to disk
+    pub fn in_memory() -> SymbolHistory {
+        SymbolHistory {
+            usage: PersistentTable::in_memory()
+        }
+    }
+
+    pub fn load(path: PathBuf) -> io::Result<SymbolHistory> {
+        Ok(SymbolHistory {
+            usage: PersistentTable::load(path)?
+        })
+    }
+
+    pub fn record(&mut self, shortcode: &str, symbol: &str) {
+        self.usage.update(|usage| {
+            let last_used = usage.values().map(|usage| usage.last_used + 1).max().unwrap_or(0);
+            let usage = usage.entry(String::from(symbol)).or_insert_with(|| SymbolUsage {
+                shortcode: String::new(),
+                count: 0,
+                last_used
+            });
+            usage.shortcode = String::from(shortcode);
+            usage.count += 1;
+            usage.last_used = last_used;
+        });
+    }
+
+    //saves any symbols recorded since the last save
+    pub fn flush(&mut self) {
+        self.usage.flush();
+    }
+
+    pub fn count(&self, symbol: &str) -> u64 {
+        self.usage.records().get(symbol).map(|usage| usage.count).unwrap_or(0)
+    }
+
+    //the most recently used symbols followed by the most frequently used ones, as (shortcode, symbol) pairs
+    pub fn suggestions(&self, limit: usize) -> Vec<(String, String)> {
+        let mut by_recency = self.usage.records().iter().collect::<Vec<_>>();
+        by_recency.sort_by_key(|(_symbol, usage)| Reverse(usage.last_used));
+        let mut by_count = self.usage.records().iter().collect::<Vec<_>>();
+        by_count.sort_by(|(s1, u1), (s2, u2)| u2.count.cmp(&u1.count).then(u2.last_used.cmp(&u1.last_used)).then(s1.cmp(s2)));
+
+        let mut seen = HashSet::new();
+        by_recency.into_iter()
+            .take(min(SymbolHistory::RECENT_COUNT, limit))
+            .chain(by_count)
+            .filter(|(symbol, _usage)| seen.insert(symbol.as_str()))
+            .take(limit)
+            .map(|(symbol, usage)| (usage.shortcode.clone(), symbol.clone()))
+            .collect()
+    }
+}
+
+lazy_static! {
+    static ref SYMBOL_HISTORY: Mutex<SymbolHistory> = Mutex::new(SymbolHistory {
+        usage: PersistentTable::load_user_file(SymbolHistory::FILENAME)
+    });
+}
+
+pub fn symbol_history() -> MutexGuard<'static, SymbolHistory> {
+    lock(&SYMBOL_HISTORY)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::persistent::parse_line;
+    use crate::symbol_history::{SymbolHistory, SymbolUsage};
+
+    #[test]
+    fn record_and_reload() {
+        let path = std::env::temp_dir().join(format!("eei_symbol_history_{}.txt", std::process::id()));
+        let mut history = SymbolHistory::load(path.clone()).unwrap();
+        history.record("heart", "❤️");
+        history.record("heart", "❤️");
+        history.record("rocket", "🚀");
+        history.flush();
+
+        let reloaded = SymbolHistory::load(path.clone()).unwrap();
+        std::fs::remove_file(path).unwrap();
+        assert_eq!(reloaded.count("❤️"), 2);
+        assert_eq!(reloaded.count("🚀"), 1);
+        assert_eq!(reloaded.count("α"), 0);
+        assert_eq!(reloaded.suggestions(5), vec![
+            (String::from("rocket"), String::from("🚀")),
+            (String::from("heart"), String::from("❤️"))
+        ]);
+    }
+
+    #[test]
+    fn suggestions() {
+        let mut history = SymbolHistory::in_memory();
+        for (shortcode, symbol) in ["a", "b", "c", "d", "e", "f", "g"].iter().zip(["α", "β", "γ", "δ", "ε", "ζ", "η"].iter()) {
+            history.record(shortcode, symbol);
+        }
+        history.record("a", "α");
+        history.record("a", "α");
+        history.record("b", "β");
+
+        let symbols = history.suggestions(7).into_iter().map(|(_shortcode, symbol)| symbol).collect::<Vec<_>>();
+        //five most recent, then the most frequent of the rest
+        assert_eq!(symbols, vec!["β", "α", "η", "ζ", "ε", "δ", "γ"]);
+        assert_eq!(history.suggestions(2).len(), 2);
+
+        assert_eq!(parse_line::<SymbolUsage>("α\talpha\t3"), None);
+        assert_eq!(parse_line::<SymbolUsage>("α\talpha\t3\t0").map(|(_symbol, usage)| usage.count), Some(3));
+    }
+}
@@ -0,0 +1,101 @@
+use std::io;
+use std::path::PathBuf;
+use std::sync::{Mutex, MutexGuard};
+use lazy_static::lazy_static;
+use crate::persistent::{lock, PersistentTable, Record};
+
+//Words the user has typed or picked, persisted as "word\tcount" lines in the same format as count_1w.txt
+pub struct UserDictionary {
+    counts: PersistentTable<u64>
+}
+
+impl Record for u64 {
+    fn parse(fields: &[&str]) -> Option<u64> {
+        match fields {
+            [count, ..] => count.parse().ok(),
+            [] => None
+        }
+    }
+
+    fn fields(&self) -> Vec<String> {
+        vec![self.to_string()]
+    }
+}
+
+impl UserDictionary {
+    const FILENAME: &'static str = "user_dictionary.txt";
+
+    //a dictionary that is never written to disk
+    pub fn in_memory() -> UserDictionary {
+        UserDictionary {
+            counts: PersistentTable::in_memory()
+        }
+    }
+
+    pub fn load(path: PathBuf) -> io::Result<UserDictionary> {
+        Ok(UserDictionary {
+            counts: PersistentTable::load(path)?
+        })
+    }
+
+    pub fn record(&mut self, word: &str) {
+        self.counts.update(|counts| *counts.entry(String::from(word)).or_insert(0) += 1);
+    }
+
+    //saves any words recorded since the last save
+    pub fn flush(&mut self) {
+        self.counts.flush();
+    }
+
+    pub fn count(&self, word: &str) -> u64 {
+        self.counts.records().get(word).copied().unwrap_or(0)
+    }
+
+    pub fn prefix_matches<'a>(&'a self, prefix: &'a str) -> impl Iterator<Item=(&'a String, u64)> + 'a {
+        self.counts.records().iter()
+            .filter(move |(word, _count)| word.starts_with(prefix))
+            .map(|(word, count)| (word, *count))
+    }
+}
+
+lazy_static! {
+    static ref USER_DICTIONARY: Mutex<UserDictionary> = Mutex::new(UserDictionary {
+        counts: PersistentTable::load_user_file(UserDictionary::FILENAME)
+    });
+}
+
+pub fn user_dictionary() -> MutexGuard<'static, UserDictionary> {
+    lock(&USER_DICTIONARY)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::persistent::parse_line;
+    use crate::user_dictionary::UserDictionary;
+
+    #[test]
+    fn record_and_reload() {
+        let path = std::env::temp_dir().join(format!("eei_user_dictionary_{}.txt", std::process::id()));
+        let mut dictionary = UserDictionary::load(path.clone()).unwrap();
+        dictionary.record("rustacean");
+        dictionary.record("rustacean");
+        dictionary.record("rustic");
+
+        //only the first word is saved straight away, the rest wait for the next save
+        assert_eq!(UserDictionary::load(path.clone()).unwrap().count("rustacean"), 1);
+        dictionary.flush();
+
+        let reloaded = UserDictionary::load(path.clone()).unwrap();
+        std::fs::remove_file(path).unwrap();
+        assert_eq!(reloaded.count("rustacean"), 2);
+        assert_eq!(reloaded.count("rust"), 0);
+        assert_eq!(reloaded.prefix_matches("rusta").collect::<Vec<_>>(), vec![(&String::from("rustacean"), 2)]);
+    }
+
+    #[test]
+    fn malformed_lines() {
+        assert_eq!(parse_line::<u64>("word\t3"), Some((String::from("word"), 3)));
+        assert_eq!(parse_line::<u64>("word"), None);
+        assert_eq!(parse_line::<u64>("\t3"), None);
+    }
+}
@@ -1,4 +1,5 @@
-use std::collections::{HashMap, HashSet};
+use std::cmp::min;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use fst::MapBuilder;
 use std::num::ParseIntError;
 use std::fs::File;
@@ -14,7 +15,8 @@ enum ParseError {
     InvalidJson(String),
     InvalidHex(ParseIntError),
     InvalidCodepoint(u32),
-    InvalidWordFreq(String)
+    InvalidWordFreq(String),
+    InvalidDictionaryArg(String)
 }
 
 //code point;class;char;entity name;entity set;note/description;CHARACTER NAME
@@ -23,7 +25,13 @@ enum ParseError {
 
 impl Display for ParseError {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{:?}", self)
+        match self {
+            InvalidJson(url) => write!(f, "unexpected emoji url {}", url),
+            InvalidHex(err) => write!(f, "invalid code point: {}", err),
+            InvalidCodepoint(codepoint) => write!(f, "{:X} is not a character", codepoint),
+            InvalidWordFreq(line) => write!(f, "invalid word frequency line {}", line),
+            InvalidDictionaryArg(arg) => write!(f, "{} is not a name=wordlist dictionary argument", arg)
+        }
     }
 }
 
@@ -44,7 +52,7 @@ fn parse_github_emoji_url(url: &String) -> Result<String, ParseError> {
     .collect::<Result<Vec<_>, _>>().map(|char_vec|char_vec.into_iter().collect::<String>())
 }
 
-fn math_symbol_shortcodes() -> Vec<(String, String)> {
+fn math_symbol_shortcodes() -> Vec<(String, String, String)> {
     let whitelist = io::BufReader::new(File::open("math_whitelist.txt").unwrap())
         .lines().collect::<Result<HashSet<String>, _>>().unwrap();
 
@@ -62,9 +70,124 @@ fn math_symbol_shortcodes() -> Vec<(String, String)> {
     rdr.into_records().filter_map(|result| {
         result.ok().map(|record| {
             let symbol = &record[2];
-            (String::from(&record[3]), String::from(symbol)) //shortcode, symbol
+            (String::from(&record[3]), String::from(symbol), String::from(&record[6])) //shortcode, symbol, name
         })
-    }).filter(|(_shortcode, symbol)| {whitelist.contains(symbol)} ).collect()
+    }).filter(|(_shortcode, symbol, _name)| {whitelist.contains(symbol)} ).collect()
+}
+
+//LaTeX commands from a local copy of http://milde.users.sourceforge.net/LUCR/Math/data/unimathsymbols.txt,
+//fields are separated by '^': code point^chars^LaTeX^unicode-math^class^category^requirements^comments
+fn latex_symbol_shortcodes() -> Result<Vec<(String, String)>, Box<dyn error::Error>> {
+    let file = match File::open("unimathsymbols.txt") {
+        Ok(file) => file,
+        Err(err) => {
+            println!("Skipping LaTeX commands, could not open unimathsymbols.txt: {}", err);
+            return Ok(Vec::new())
+        }
+    };
+
+    let mut shortcodes = Vec::new();
+    for line in io::BufReader::new(file).lines() {
+        let line = line?;
+        if line.starts_with('#') {
+            continue;
+        }
+        let fields = line.split('^').collect::<Vec<_>>();
+        if let [_codepoint, symbol, latex, unicode_math, _class, _category, _requirements, comments] = fields.as_slice() {
+            //other commands for the same symbol are listed in the comments as "= \command (package)"
+            let aliases = comments.split(',')
+                .filter_map(|comment| comment.trim().strip_prefix('='))
+                .filter_map(|alias| alias.split_whitespace().next());
+            let commands = [*latex, *unicode_math].iter().copied().chain(aliases)
+                .filter(|command| command.starts_with('\\'))
+                .collect::<HashSet<_>>();
+            shortcodes.extend(commands.into_iter().map(|command| (String::from(command), String::from(*symbol))));
+        }
+    }
+    Ok(shortcodes)
+}
+
+//official character names from a local copy of https://www.unicode.org/Public/UCD/latest/ucd/UnicodeData.txt
+fn load_character_names() -> Result<HashMap<char, String>, Box<dyn error::Error>> {
+    let file = match File::open("UnicodeData.txt") {
+        Ok(file) => file,
+        Err(err) => {
+            println!("Only math symbols will have names and character_names.fst is left as it is, could not open UnicodeData.txt: {}", err);
+            return Ok(HashMap::new())
+        }
+    };
+
+    let mut names = HashMap::new();
+    for line in io::BufReader::new(file).lines() {
+        let line = line?;
+        let mut fields = line.split(';');
+        if let (Some(codepoint), Some(name)) = (fields.next(), fields.next()) {
+            //skip "<control>" and the "<CJK Ideograph, First>" style range markers
+            if !name.starts_with('<') {
+                names.insert(parse_unicode(codepoint)?, String::from(name));
+            }
+        }
+    }
+    Ok(names)
+}
+
+//emoji sequences are named after their parts, leaving out joiners and variation selectors
+fn symbol_name(symbol: &str, character_names: &HashMap<char, String>) -> Option<String> {
+    symbol.chars()
+        .filter(|c| !matches!(c, '\u{200D}' | '\u{FE0E}' | '\u{FE0F}'))
+        .map(|c| character_names.get(&c).cloned())
+        .collect::<Option<Vec<_>>>()
+        .map(|names| names.join(", "))
+}
+
+//character_names.fst maps "codepoint\x1fname" to the code point, with the code point as six hex digits so
+//the keys sort by it and the engine can name any character, not just the ones in the symbol table
+fn write_character_names(character_names: &HashMap<char, String>) -> Result<(), Box<dyn error::Error>> {
+    let mut names = character_names.iter()
+        .map(|(c, name)| (format!("{:06X}\x1f{}", *c as u32, name.to_lowercase()), *c as u64))
+        .collect::<Vec<_>>();
+    names.sort();
+
+    let writer = io::BufWriter::new(File::create("character_names.fst")?);
+    let mut map_builder = MapBuilder::new(writer)?;
+    for (key, codepoint) in names.iter() {
+        map_builder.insert(key, *codepoint)?;
+    }
+    map_builder.finish()?;
+
+    println!("Wrote names for {characters} characters", characters=names.len());
+    Ok(())
+}
+
+//CLDR annotations leave out the variation selectors that GitHub's emoji have
+fn annotation_key(symbol: &str) -> String {
+    symbol.chars().filter(|c| *c != '\u{FE0F}').collect()
+}
+
+//search keywords from a local copy of https://github.com/unicode-org/cldr/blob/main/common/annotations/en.xml,
+//e.g. <annotation cp="😂">face | joy | laugh | tears</annotation>
+fn load_annotations() -> Result<HashMap<String, Vec<String>>, Box<dyn error::Error>> {
+    let text = match std::fs::read_to_string("annotations.xml") {
+        Ok(text) => text,
+        Err(err) => {
+            println!("Skipping keywords, could not open annotations.xml: {}", err);
+            return Ok(HashMap::new())
+        }
+    };
+
+    let document = roxmltree::Document::parse(text.as_str())?;
+    let annotations = document.descendants()
+        //the "tts" annotations are just the emoji's name, which symbol_names.bin already covers
+        .filter(|node| node.has_tag_name("annotation") && node.attribute("type").is_none())
+        .filter_map(|node| {
+            let keywords = node.text()?.split('|')
+                .map(|keyword| keyword.trim().to_lowercase().replace(' ', "_"))
+                .filter(|keyword| !keyword.is_empty())
+                .collect::<Vec<_>>();
+            Some((annotation_key(node.attribute("cp")?), keywords))
+        })
+        .collect();
+    Ok(annotations)
 }
 
 fn github_emoji_shortcodes() -> Vec<(String, String)> {
@@ -87,7 +210,121 @@ fn github_emoji_shortcodes() -> Vec<(String, String)> {
 }
 
 
-fn write_symbols_and_shortcodes(mut shortcodes_symbols: Vec<(String, String)>) -> Result<(), Box<dyn error::Error>> {
+//emoji-test.txt keeps joiners and variation selectors, which GitHub's image names leave out
+fn emoji_key(symbol: &str) -> String {
+    symbol.chars().filter(|c| !matches!(c, '\u{200D}' | '\u{FE0F}')).collect()
+}
+
+//the fully qualified emoji and their names from a local copy of https://unicode.org/Public/emoji/latest/emoji-test.txt,
+//e.g. 1F468 200D 1F469 200D 1F467 ; fully-qualified # 👨‍👩‍👧 E2.0 family: man, woman, girl
+fn load_emoji_test() -> Result<Vec<(String, String)>, Box<dyn error::Error>> {
+    let file = match File::open("emoji-test.txt") {
+        Ok(file) => file,
+        Err(err) => {
+            println!("Using GitHub's emoji as they are, could not open emoji-test.txt: {}", err);
+            return Ok(Vec::new())
+        }
+    };
+
+    let mut emoji = Vec::new();
+    for line in io::BufReader::new(file).lines() {
+        let line = line?;
+        if line.starts_with('#') {
+            continue;
+        }
+        let (codepoints, status, comment) = match (line.split_once(';'), line.split_once('#')) {
+            (Some((codepoints, rest)), Some((_, comment))) => (codepoints, rest.split('#').next().unwrap_or(""), comment),
+            _ => continue
+        };
+        if status.trim() != "fully-qualified" {
+            continue;
+        }
+        let symbol = codepoints.split_whitespace().map(parse_unicode).collect::<Result<String, _>>()?;
+        //the comment is the emoji, the version it was added in, and then its name
+        let name = comment.trim().splitn(3, ' ').nth(2).unwrap_or("");
+        emoji.push((symbol, String::from(name)));
+    }
+    Ok(emoji)
+}
+
+//turns a name like "flag: United States" into flag_united_states
+//Punctuation that makes up a whole name part, like in "keycap: *", is spelled out so it isn't lost
+fn shortcode_from_name(name: &str) -> String {
+    name.to_lowercase()
+        .split(' ')
+        .map(|part| match part {
+            "*" => "asterisk",
+            "#" => "number sign",
+            _ => part
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join("_")
+}
+
+//GitHub's emoji replaced by the fully qualified sequences from emoji-test.txt, and shortcodes made from the
+//names of the emoji GitHub doesn't have. GitHub shortcodes that needed fixing or have no emoji are reported.
+fn qualify_emoji_shortcodes(github_shortcodes: Vec<(String, String)>, emoji_test: &[(String, String)]) -> Vec<(String, String)> {
+    if emoji_test.is_empty() {
+        return github_shortcodes;
+    }
+
+    let mut qualified = HashMap::new();
+    for (symbol, _name) in emoji_test.iter() {
+        qualified.entry(emoji_key(symbol)).or_insert(symbol);
+    }
+
+    let mut unqualified = Vec::new();
+    let mut missing = Vec::new();
+    let mut shortcodes = github_shortcodes.into_iter().filter_map(|(shortcode, symbol)| {
+        match qualified.get(&emoji_key(symbol.as_str())) {
+            Some(qualified_symbol) if **qualified_symbol == symbol => Some((shortcode, symbol)),
+            Some(qualified_symbol) => {
+                unqualified.push(shortcode.clone());
+                Some((shortcode, (*qualified_symbol).clone()))
+            }
+            None => {
+                missing.push(shortcode);
+                None
+            }
+        }
+    }).collect::<Vec<_>>();
+
+    let mut taken_shortcodes = shortcodes.iter().map(|(shortcode, _symbol)| shortcode.clone()).collect::<HashSet<_>>();
+    let covered = shortcodes.iter().map(|(_shortcode, symbol)| symbol.clone()).collect::<HashSet<_>>();
+    //skin tones are applied when searching, so toned emoji don't get shortcodes of their own.
+    //Names that still give a shortcode already taken get a number after it, so every emoji keeps one
+    let named = emoji_test.iter()
+        .filter(|(symbol, _name)| !covered.contains(symbol) && !symbol.chars().any(|c| ('\u{1F3FB}'..='\u{1F3FF}').contains(&c)))
+        .map(|(symbol, name)| (shortcode_from_name(name), symbol.clone()))
+        .filter(|(shortcode, _symbol)| !shortcode.is_empty())
+        .map(|(shortcode, symbol)| {
+            let unique = (1..).map(|n| if n == 1 { shortcode.clone() } else { format!("{}_{}", shortcode, n) })
+                .find(|candidate| !taken_shortcodes.contains(candidate))
+                .unwrap();
+            taken_shortcodes.insert(unique.clone());
+            (unique, symbol)
+        })
+        .collect::<Vec<_>>();
+
+    unqualified.sort();
+    missing.sort();
+    println!("Qualified {count} GitHub shortcodes with unqualified sequences: {shortcodes}",
+             count=unqualified.len(), shortcodes=unqualified.join(", "));
+    println!("Dropped {count} GitHub shortcodes with no sequence in emoji-test.txt: {shortcodes}",
+             count=missing.len(), shortcodes=missing.join(", "));
+    println!("Added {count} shortcodes from emoji-test.txt names for emoji GitHub doesn't have", count=named.len());
+
+    shortcodes.extend(named);
+    shortcodes
+}
+
+
+fn write_symbols_and_shortcodes(mut shortcodes_symbols: Vec<(String, String)>, character_names: &HashMap<char, String>,
+                                annotations: &HashMap<String, Vec<String>>) -> Result<(), Box<dyn error::Error>> {
     let writer = io::BufWriter::new(File::create("shortcodes.fst")?);
     let mut map_builder = MapBuilder::new(writer)?;
 
@@ -112,13 +349,44 @@ fn write_symbols_and_shortcodes(mut shortcodes_symbols: Vec<(String, String)>) -
     let mut symbol_file = File::create("symbols.bin")?;
     symbol_file.write_all(&bincode::serialize(&symbols)?)?;
 
-    println!("Wrote {shortcodes} shortcodes for {symbols} symbols",
+    //names are stored in the same order as the symbols, with an empty name for unnamed symbols
+    let names = symbols.iter()
+        .map(|symbol| symbol_name(symbol, character_names).unwrap_or_default())
+        .collect::<Vec<String>>();
+    let mut names_file = File::create("symbol_names.bin")?;
+    names_file.write_all(&bincode::serialize(&names)?)?;
+
+    println!("Wrote {shortcodes} shortcodes for {symbols} symbols, of which {named} have names",
              shortcodes=shortcodes_symbols.len(),
-             symbols=symbols.len());
+             symbols=symbols.len(),
+             named=names.iter().filter(|name| !name.is_empty()).count());
+
+    //keywords point at symbol ids, so they are always rewritten along with the symbols, if only to be empty
+    write_keywords(&symbol_id_map, annotations)?;
 
     Ok(())
 }
 
+//keywords.fst maps "keyword\x1fsymbol" to the symbol id, so a keyword can point to several symbols
+fn write_keywords(symbol_id_map: &HashMap<&String, u64>, annotations: &HashMap<String, Vec<String>>) -> Result<(), Box<dyn error::Error>> {
+    let mut keywords = symbol_id_map.iter()
+        .filter_map(|(symbol, id)| annotations.get(&annotation_key(symbol)).map(|keywords| (symbol, id, keywords)))
+        .flat_map(|(symbol, id, keywords)| keywords.iter().map(move |keyword| (format!("{}\x1f{}", keyword, symbol), *id)))
+        .collect::<Vec<_>>();
+    keywords.sort();
+    keywords.dedup_by(|(k1, _i1), (k2, _i2)| k1 == k2);
+
+    let writer = io::BufWriter::new(File::create("keywords.fst")?);
+    let mut map_builder = MapBuilder::new(writer)?;
+    for (key, id) in keywords.iter() {
+        map_builder.insert(key, *id)?;
+    }
+    map_builder.finish()?;
+
+    println!("Wrote {keywords} keywords", keywords=keywords.len());
+    Ok(())
+}
+
 fn load_word_freq_data() -> Result<HashMap<String, u64>, Box<dyn error::Error>> {
     let lines = io::BufReader::new(File::open("count_1w.txt")?)
         .lines()
@@ -139,58 +407,247 @@ fn load_word_freq_data() -> Result<HashMap<String, u64>, Box<dyn error::Error>>
     }).collect::<Result<HashMap<String, u64>, ParseError>>()?)
 }
 
-fn process_dictionary() -> Result<(), Box<dyn error::Error>> {
-    let writer = io::BufWriter::new(File::create("dictionary.fst")?);
+//dictionary values are the word frequency, with the capitalised letters of the word marked in the bits above it
+const DICTIONARY_CASE_SHIFT: u32 = 48;
+const DICTIONARY_FREQUENCY_MASK: u64 = (1 << DICTIONARY_CASE_SHIFT) - 1;
+
+//bit n is set if the nth character is capitalised, e.g. 0b10 for "iPhone"
+fn dictionary_case_mask(word: &str) -> u64 {
+    word.chars()
+        .take((64 - DICTIONARY_CASE_SHIFT) as usize)
+        .enumerate()
+        .filter(|(_idx, c)| c.is_uppercase())
+        .fold(0, |mask, (idx, _c)| mask | 1 << idx)
+}
+
+//count_1w.txt only has unaccented words, so accented ones like "café" are given the frequency of "cafe"
+fn without_accents(word: &str) -> String {
+    const ACCENTS: [(char, &str); 15] = [
+        ('a', "àáâãäåāăą"), ('c', "çćč"), ('d', "ď"), ('e', "èéêëēėęě"), ('g', "ğ"), ('i', "ìíîïīį"), ('l', "ł"), ('n', "ñńň"),
+        ('o', "òóôõöøōő"), ('r', "ŕř"), ('s', "śšş"), ('t', "ť"), ('u', "ùúûüūůűų"), ('y', "ýÿ"), ('z', "źżž")
+    ];
+    word.chars()
+        .map(|c| ACCENTS.iter()
+            .find(|(_letter, accented)| accented.contains(c))
+            .map_or(c, |(letter, _accented)| *letter))
+        .collect()
+}
+
+//unmunch writes words in the dictionary's own encoding, which is often ISO-8859-1 rather than
+//UTF-8, so fall back to that instead of failing on words like "café"
+fn decode_dictionary_line(line: Vec<u8>) -> String {
+    match String::from_utf8(line) {
+        Ok(line) => line,
+        Err(err) => err.into_bytes().into_iter().map(char::from).collect()
+    }
+}
+
+fn process_dictionary(word_freq: &HashMap<String, u64>, source: &str, output: &str) -> Result<(), Box<dyn error::Error>> {
+    let writer = io::BufWriter::new(File::create(output)?);
     let mut map_builder = MapBuilder::new(writer)?;
 
-    let mut lines = io::BufReader::new(File::open("hunspell_US.txt")?)
-        .lines()
+    let lines = io::BufReader::new(File::open(source)?)
+        .split(b'\n')
         .map(|line_res| {
             line_res.map(|line| {
-                line.to_lowercase()
+                String::from(decode_dictionary_line(line).trim())
             })
         })
+        .filter(|line_res| line_res.as_ref().map(|line| !line.is_empty()).unwrap_or(true))
         .collect::<Result<Vec<_>, _>>()?;
 
-    //must be in lexographical order to build the FST
-    lines.sort();
-    lines.dedup();
-    let word_freq = load_word_freq_data()?;
+    //words are looked up in lowercase, keeping their capitalisation unless they are also listed in
+    //lowercase, like "may" and "May". The BTreeMap keeps them in the lexographical order the FST needs.
+    let mut words: BTreeMap<String, u64> = BTreeMap::new();
+    for line in lines.iter() {
+        let case_mask = words.entry(line.to_lowercase()).or_insert(u64::MAX);
+        *case_mask = min(*case_mask, dictionary_case_mask(line));
+    }
 
     let mut words_without_freq = 0;
 
-    for line in lines.iter() {
-        map_builder.insert(line,
-                           *word_freq.get(line.as_str()).unwrap_or_else(|| {
+    for (word, case_mask) in words.iter() {
+        let freq = *word_freq.get(word.as_str()).or_else(|| word_freq.get(without_accents(word).as_str())).unwrap_or_else(|| {
                                                 words_without_freq += 1;
                                                 &0
-                                            }))?;
+                                            });
+        map_builder.insert(word, case_mask << DICTIONARY_CASE_SHIFT | min(freq, DICTIONARY_FREQUENCY_MASK))?;
+    }
+
+    let words_with_freq = words.len() - words_without_freq;
+
+    map_builder.finish()?;
+    println!("Wrote {entries} dictionary entries to {output}, of which {with_freq} had frequency ({perc:.2}%)",
+             entries=words.len(), output=output, with_freq=words_with_freq,
+             perc=(words_with_freq as f64 / words.len() as f64));
+    Ok(())
+}
+
+//bigrams seen fewer times than this are mostly noise from the corpus
+const MIN_BIGRAM_COUNT: u64 = 2;
+
+//apostrophes and hyphens are kept inside words, for contractions and compounds like "doesn't" and "well-known"
+fn corpus_words(line: &str) -> Vec<String> {
+    line.split(|c: char| !(c.is_alphabetic() || c == '\'' || c == '-'))
+        .map(|word| word.trim_matches(|c| c == '\'' || c == '-').to_lowercase())
+        .filter(|word| !word.is_empty())
+        .collect()
+}
+
+fn process_bigrams() -> Result<(), Box<dyn error::Error>> {
+    let corpus = match File::open("corpus.txt") {
+        Ok(file) => file,
+        Err(err) => {
+            println!("Skipping bigrams, could not open corpus.txt: {}", err);
+            return Ok(())
+        }
+    };
+
+    let mut bigram_counts: HashMap<String, u64> = HashMap::new();
+    for line in io::BufReader::new(corpus).lines() {
+        let words = corpus_words(line?.as_str());
+        for pair in words.windows(2) {
+            *bigram_counts.entry(format!("{} {}", pair[0], pair[1])).or_insert(0) += 1;
+        }
     }
 
-    let words_with_freq = lines.len() - words_without_freq;
+    let mut bigrams = bigram_counts.into_iter()
+        .filter(|(_bigram, count)| *count >= MIN_BIGRAM_COUNT)
+        .collect::<Vec<_>>();
 
+    //must be in lexographical order to build the FST
+    bigrams.sort();
+
+    let writer = io::BufWriter::new(File::create("bigrams.fst")?);
+    let mut map_builder = MapBuilder::new(writer)?;
+    for (bigram, count) in bigrams.iter() {
+        map_builder.insert(bigram, *count)?;
+    }
     map_builder.finish()?;
-    println!("Wrote {entries} dictionary entries, of which {with_freq} had frequency ({perc:.2}%)",
-             entries=lines.len(), with_freq=words_with_freq,
-             perc=(words_with_freq as f64 / lines.len() as f64));
+
+    println!("Wrote {bigrams} bigrams", bigrams=bigrams.len());
     Ok(())
 }
 
+//extra dictionaries are given on the command line as name=wordlist, and written to dictionary_name.fst
+fn parse_dictionary_args() -> Result<Vec<(String, String)>, ParseError> {
+    std::env::args().skip(1).map(|arg| {
+        match arg.split_once('=') {
+            Some((name, source)) if !name.is_empty() && !source.is_empty() => {
+                Ok((String::from(source), format!("dictionary_{}.fst", name)))
+            }
+            _ => Err(InvalidDictionaryArg(arg.clone()))
+        }
+    }).collect()
+}
+
 fn main() -> Result<(), Box<dyn error::Error>>{
+    let extra_dictionaries = parse_dictionary_args()?;
+
     println!("Fetching math symbols");
     let math_symbols = math_symbol_shortcodes();
 
+    let mut character_names = load_character_names()?;
+    //without UnicodeData.txt the shipped character names are kept rather than cut down to the math symbols
+    let write_names = !character_names.is_empty();
+    for (_shortcode, symbol, name) in math_symbols.iter() {
+        let mut chars = symbol.chars();
+        if let (Some(c), None) = (chars.next(), chars.next()) {
+            character_names.entry(c).or_insert_with(|| name.clone());
+        }
+    }
+    let math_symbols = math_symbols.into_iter()
+        .map(|(shortcode, symbol, _name)| (shortcode, symbol))
+        .collect::<Vec<_>>();
+
     println!("Fetching shortcodes from github");
-    let shortcodes = github_emoji_shortcodes();
+    let shortcodes = qualify_emoji_shortcodes(github_emoji_shortcodes(), &load_emoji_test()?);
 
-    let all_symbols = [math_symbols, shortcodes].concat();
+    let latex_symbols = latex_symbol_shortcodes()?;
+
+    let all_symbols = [math_symbols, latex_symbols, shortcodes].concat();
+    let annotations = load_annotations()?;
 
     println!("Writing symbols and shortcodes to files");
-    write_symbols_and_shortcodes(all_symbols)?;
+    write_symbols_and_shortcodes(all_symbols, &character_names, &annotations)?;
+    if write_names {
+        write_character_names(&character_names)?;
+    }
     println!("Processing dictionary");
-    process_dictionary()?;
+    let word_freq = load_word_freq_data()?;
+    process_dictionary(&word_freq, "hunspell_US.txt", "dictionary.fst")?;
+    for (source, output) in extra_dictionaries.iter() {
+        println!("Processing dictionary {}", source);
+        process_dictionary(&word_freq, source, output)?;
+    }
+    println!("Processing bigrams");
+    process_bigrams()?;
 
     Ok(())
 }
 
+#[cfg(test)]
+mod tests {
+    use crate::{qualify_emoji_shortcodes, shortcode_from_name, without_accents};
+
+    fn pairs(entries: &[(&str, &str)]) -> Vec<(String, String)> {
+        entries.iter().map(|(a, b)| (String::from(*a), String::from(*b))).collect()
+    }
+
+    #[test]
+    fn shortcodes_from_names() {
+        assert_eq!(shortcode_from_name("face with spiral eyes"), "face_with_spiral_eyes");
+        assert_eq!(shortcode_from_name("flag: United States"), "flag_united_states");
+        assert_eq!(shortcode_from_name("family: man, woman, girl"), "family_man_woman_girl");
+        assert_eq!(shortcode_from_name("keycap: *"), "keycap_asterisk");
+        assert_eq!(shortcode_from_name("keycap: #"), "keycap_number_sign");
+        assert_eq!(shortcode_from_name("keycap: 10"), "keycap_10");
+    }
 
+    #[test]
+    fn unaccented_words() {
+        assert_eq!(without_accents("café"), "cafe");
+        assert_eq!(without_accents("naïveté"), "naivete");
+        assert_eq!(without_accents("jalapeño"), "jalapeno");
+        assert_eq!(without_accents("smörgåsbord"), "smorgasbord");
+        assert_eq!(without_accents("doppelgänger"), "doppelganger");
+    }
+
+    #[test]
+    fn qualified_shortcodes() {
+        let emoji_test = pairs(&[
+            ("\u{2764}\u{FE0F}", "red heart"),
+            ("👨\u{200D}👩\u{200D}👧", "family: man, woman, girl"),
+            ("🚀", "rocket"),
+            ("😵\u{200D}💫", "face with spiral eyes"),
+            ("👍🏽", "thumbs up: medium skin tone"),
+            ("*\u{FE0F}\u{20E3}", "keycap: *"),
+            ("#\u{FE0F}\u{20E3}", "keycap: #"),
+            //a name whose shortcode GitHub already uses
+            ("🛸", "rocket")
+        ]);
+        let github = pairs(&[
+            //GitHub leaves out the variation selector and the joiners
+            ("heart", "\u{2764}"),
+            ("family_man_woman_girl", "👨👩👧"),
+            ("rocket", "🚀"),
+            //not an emoji at all
+            ("octocat", "\u{E000}")
+        ]);
+
+        let mut shortcodes = qualify_emoji_shortcodes(github.clone(), &emoji_test);
+        shortcodes.sort();
+        assert_eq!(shortcodes, pairs(&[
+            ("face_with_spiral_eyes", "😵\u{200D}💫"),
+            ("family_man_woman_girl", "👨\u{200D}👩\u{200D}👧"),
+            ("heart", "\u{2764}\u{FE0F}"),
+            ("keycap_asterisk", "*\u{FE0F}\u{20E3}"),
+            ("keycap_number_sign", "#\u{FE0F}\u{20E3}"),
+            ("rocket", "🚀"),
+            ("rocket_2", "🛸")
+        ]));
+
+        //without emoji-test.txt GitHub's emoji are used as they are
+        assert_eq!(qualify_emoji_shortcodes(github.clone(), &[]), github);
+    }
+}